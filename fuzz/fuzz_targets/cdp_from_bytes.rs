@@ -19,8 +19,11 @@ pub fn debug_init() {
 fuzz_target!(|data: &[u8]| {
     debug_init();
     let mut parser = CDPParser::new();
-    if let Ok(_) = parser.parse(data) {
-        let mut writer = CDPWriter::new(parser.framerate().unwrap());
+    if parser.parse(data).is_ok() {
+        let Some(framerate) = parser.framerate() else {
+            return;
+        };
+        let mut writer = CDPWriter::new(framerate);
         while let Some(p) = parser.pop_packet() {
             info!("parsed {p:?}");
             writer.push_packet(p);
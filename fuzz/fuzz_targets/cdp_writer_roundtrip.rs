@@ -0,0 +1,140 @@
+#![no_main]
+use libfuzzer_sys::arbitrary::{self, Arbitrary, Unstructured};
+use libfuzzer_sys::fuzz_target;
+
+use cea708_types::{tables, Cea608, DTVCCPacket, Service};
+use cdp_types::{CDPParser, CDPWriter, Framerate, TimeCode};
+
+use once_cell::sync::Lazy;
+
+#[macro_use]
+extern crate log;
+
+pub fn debug_init() {
+    static TRACING: Lazy<()> = Lazy::new(|| {
+        env_logger::init()
+    });
+
+    Lazy::force(&TRACING);
+}
+
+#[derive(Debug)]
+struct Input {
+    framerate: Framerate,
+    time_code: Option<TimeCode>,
+    sequence: u16,
+    caption_service_active: bool,
+    cea608: Vec<(bool, u8, u8)>,
+    services: Vec<(u8, String)>,
+}
+
+impl<'a> Arbitrary<'a> for Input {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Self {
+            framerate: u.arbitrary()?,
+            time_code: u.arbitrary()?,
+            sequence: u.arbitrary()?,
+            caption_service_active: u.arbitrary()?,
+            cea608: u.arbitrary()?,
+            services: u.arbitrary()?,
+        })
+    }
+}
+
+fuzz_target!(|input: Input| {
+    debug_init();
+    info!("{input:?}");
+
+    let mut packet = DTVCCPacket::new(0);
+    for (service_no, text) in input.services.iter() {
+        // cea708-types does not round trip the extended service numbers above 6
+        let mut service = Service::new(service_no % 6 + 1);
+        for code in text.chars().filter_map(tables::Code::from_char) {
+            if service.push_code(&code).is_err() {
+                break;
+            }
+        }
+        if service.codes().is_empty() || packet.push_service(service).is_err() {
+            continue;
+        }
+    }
+
+    let mut writer = CDPWriter::new(input.framerate);
+    writer.set_time_code(input.time_code);
+    writer.set_sequence_count(input.sequence);
+    writer.set_auto_sequence(true);
+    writer.set_caption_service_active(input.caption_service_active);
+    let expected_services = packet
+        .services()
+        .iter()
+        .map(|service| (service.number(), service.codes().to_vec()))
+        .collect::<Vec<_>>();
+    if !expected_services.is_empty() {
+        writer.push_packet(packet);
+    }
+    // 0x80 0x80 is CEA-608 padding that is not written
+    let expected_cea608 = input
+        .cea608
+        .iter()
+        .map(|&(field2, byte0, byte1)| (field2, byte0 | 0x80, byte1 | 0x80))
+        .filter(|&(_, byte0, byte1)| (byte0, byte1) != (0x80, 0x80))
+        .map(|(field2, byte0, byte1)| {
+            if field2 {
+                Cea608::Field2(byte0, byte1)
+            } else {
+                Cea608::Field1(byte0, byte1)
+            }
+        })
+        .collect::<Vec<_>>();
+    for cea608 in expected_cea608.iter() {
+        writer.push_cea608(*cea608);
+    }
+
+    // the queued 708 data may be spread over multiple CDPs
+    let mut parser = CDPParser::new();
+    let mut parsed_services = vec![];
+    let mut parsed_cea608 = vec![];
+    // at least one CEA-608 field is written in every CDP
+    let n_cdps = expected_cea608.len() * 2 + 32;
+    for i in 0..n_cdps {
+        let mut written = vec![];
+        writer.write(&mut written).unwrap();
+        parser.parse(&written).unwrap();
+        assert_eq!(parser.framerate(), Some(input.framerate));
+        assert_eq!(parser.time_code(), input.time_code);
+        assert_eq!(parser.sequence(), input.sequence.wrapping_add(i as u16));
+        assert_eq!(parser.caption_service_active(), input.caption_service_active);
+        // field 1 padding is written before field 2 data without field 1 data
+        parsed_cea608.extend(
+            parser
+                .cea608()
+                .unwrap_or_default()
+                .iter()
+                .filter(|&&pair| pair != Cea608::Field1(0x80, 0x80)),
+        );
+        for packet in parser.drain_packets() {
+            parsed_services.extend(
+                packet
+                    .services()
+                    .iter()
+                    .map(|service| (service.number(), service.codes().to_vec())),
+            );
+        }
+    }
+
+    // the order of the CEA-608 byte pairs is only kept within each field
+    let field = |cea608: &[Cea608], field2: bool| {
+        cea608
+            .iter()
+            .filter(|pair| matches!(pair, Cea608::Field2(..)) == field2)
+            .copied()
+            .collect::<Vec<_>>()
+    };
+    for field2 in [false, true] {
+        assert_eq!(
+            field(&parsed_cea608, field2),
+            field(&expected_cea608, field2)
+        );
+    }
+    assert_eq!(parsed_services, expected_services);
+});
@@ -0,0 +1,313 @@
+// Copyright (C) 2023 Matthew Waters <matthew@centricular.com>
+//
+// Licensed under the MIT license <LICENSE-MIT> or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! ANC packet support
+//!
+//! CDPs are carried in the VANC of a video signal (and e.g. in SMPTE 436M tracks of MXF files)
+//! as an ANC packet with a DID of 0x61 and an SDID of 0x01.  An ANC packet consists of an
+//! optional ancillary data flag, the DID, SDID and data count words, the user data words
+//! containing the CDP and a checksum word.
+//!
+//! Both the 10-bit word encoding of SMPTE 291M and the 8-bit encoding containing only the 8 least
+//! significant bits of each word are supported for reading and writing.
+
+use crate::{CDPParser, ParserError};
+
+/// The DID and SDID of an ANC packet containing a CDP
+pub(crate) const ANC_CDP_ID: [u8; 2] = [0x61, 0x01];
+
+/// The ancillary data flag that may precede a 10-bit ANC packet
+const ADF: [u16; 3] = [0x000, 0x3ff, 0x3ff];
+
+/// The ancillary data flag that may precede an 8-bit ANC packet
+const ADF_8BIT: [u8; 3] = [0x00, 0xff, 0xff];
+
+/// Errors that can occur when reading ANC packets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AncError {
+    /// The ANC packet is shorter than its data count requires
+    Truncated,
+    /// The ANC packet does not contain a CDP
+    NotCdp,
+    /// The checksum of the ANC packet does not match its contents
+    ChecksumMismatch {
+        /// The checksum stored in the ANC packet
+        found: u16,
+        /// The checksum calculated from the ANC packet
+        calculated: u16,
+    },
+    /// Parsing the contained CDP failed
+    Parser(ParserError),
+}
+
+impl From<ParserError> for AncError {
+    fn from(value: ParserError) -> Self {
+        AncError::Parser(value)
+    }
+}
+
+impl std::fmt::Display for AncError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(&format!("{self:?}"))
+    }
+}
+
+/// The 9-bit checksum of 10-bit ANC words with bit 9 set to the inverse of bit 8
+fn checksum(words: &[u16]) -> u16 {
+    let sum = words
+        .iter()
+        .fold(0u16, |sum, word| sum.wrapping_add(word & 0x1ff))
+        & 0x1ff;
+    sum | ((!sum & 0x100) << 1)
+}
+
+/// A 10-bit ANC word for `data` with bit 8 set to the even parity of `data` and bit 9 set to the
+/// inverse of bit 8
+fn word(data: u8) -> u16 {
+    let parity = (data.count_ones() as u16 & 1) << 8;
+    data as u16 | parity | ((!parity & 0x100) << 1)
+}
+
+/// The 8-bit checksum of 8-bit ANC words
+fn checksum_8bit(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |sum, d| sum.wrapping_add(*d))
+}
+
+/// Extract the CDP from an ANC packet of 10-bit words.
+///
+/// The ancillary data flag (`0x000 0x3ff 0x3ff`) is optional.  Any words following the checksum
+/// word are ignored.
+///
+/// ```
+/// # use cdp_types::anc;
+/// let words = [
+///     0x000, 0x3ff, 0x3ff, 0x161, 0x101, 0x110, 0x296, 0x269, 0x110, 0x23f, 0x241, 0x212,
+///     0x134, 0x272, 0x2e1, 0x2fc, 0x194, 0x120, 0x274, 0x212, 0x134, 0x16e, 0x172,
+/// ];
+/// let cdp = anc::cdp_from_anc(&words).unwrap();
+/// assert_eq!(cdp.len(), 16);
+/// assert_eq!(cdp[..2], [0x96, 0x69]);
+/// ```
+pub fn cdp_from_anc(words: &[u16]) -> Result<Vec<u8>, AncError> {
+    let words = words.strip_prefix(&ADF).unwrap_or(words);
+    if words.len() < 3 {
+        return Err(AncError::Truncated);
+    }
+    if [words[0] as u8, words[1] as u8] != ANC_CDP_ID {
+        return Err(AncError::NotCdp);
+    }
+    let len = (words[2] & 0xff) as usize;
+    if words.len() < 3 + len + 1 {
+        return Err(AncError::Truncated);
+    }
+    let found = words[3 + len] & 0x3ff;
+    let calculated = checksum(&words[..3 + len]);
+    if found != calculated {
+        return Err(AncError::ChecksumMismatch { found, calculated });
+    }
+    Ok(words[3..3 + len].iter().map(|word| *word as u8).collect())
+}
+
+/// Extract the CDP from an ANC packet containing only the 8 least significant bits of each word.
+///
+/// The ancillary data flag (`0x00 0xff 0xff`) is optional.  The checksum is the 8 least
+/// significant bits of the sum of the DID, SDID, data count and user data words.  Any data
+/// following the checksum is ignored.
+pub fn cdp_from_anc_8bit(data: &[u8]) -> Result<&[u8], AncError> {
+    let data = data.strip_prefix(&ADF_8BIT).unwrap_or(data);
+    if data.len() < 3 {
+        return Err(AncError::Truncated);
+    }
+    if data[..2] != ANC_CDP_ID {
+        return Err(AncError::NotCdp);
+    }
+    let len = data[2] as usize;
+    if data.len() < 3 + len + 1 {
+        return Err(AncError::Truncated);
+    }
+    let found = data[3 + len];
+    let calculated = checksum_8bit(&data[..3 + len]);
+    if found != calculated {
+        return Err(AncError::ChecksumMismatch {
+            found: found as u16,
+            calculated: calculated as u16,
+        });
+    }
+    Ok(&data[3..3 + len])
+}
+
+/// Parse the CDP contained in an ANC packet of 10-bit words with `parser`.
+/// See [`cdp_from_anc`].
+pub fn parse_anc(parser: &mut CDPParser, words: &[u16]) -> Result<(), AncError> {
+    let cdp = cdp_from_anc(words)?;
+    parser.parse(&cdp)?;
+    Ok(())
+}
+
+/// Parse the CDP contained in an 8-bit ANC packet with `parser`.  See [`cdp_from_anc_8bit`].
+pub fn parse_anc_8bit(parser: &mut CDPParser, data: &[u8]) -> Result<(), AncError> {
+    let cdp = cdp_from_anc_8bit(data)?;
+    parser.parse(cdp)?;
+    Ok(())
+}
+
+/// Wrap a CDP in an ANC packet of 10-bit words.
+///
+/// The returned words start with the DID and do not contain the ancillary data flag.
+///
+/// # Panics
+///
+/// If `cdp` is longer than 255 bytes.
+pub fn wrap_cdp(cdp: &[u8]) -> Vec<u16> {
+    assert!(
+        cdp.len() <= u8::MAX as usize,
+        "CDP too large for an ANC packet"
+    );
+    let mut words = Vec::with_capacity(cdp.len() + 4);
+    words.extend(ANC_CDP_ID.iter().map(|&d| word(d)));
+    words.push(word(cdp.len() as u8));
+    words.extend(cdp.iter().map(|&d| word(d)));
+    words.push(checksum(&words));
+    words
+}
+
+/// Wrap a CDP in an ANC packet containing only the 8 least significant bits of each word.
+///
+/// The returned data starts with the DID and does not contain the ancillary data flag.
+///
+/// # Panics
+///
+/// If `cdp` is longer than 255 bytes.
+pub fn wrap_cdp_8bit(cdp: &[u8]) -> Vec<u8> {
+    assert!(
+        cdp.len() <= u8::MAX as usize,
+        "CDP too large for an ANC packet"
+    );
+    let mut data = Vec::with_capacity(cdp.len() + 4);
+    data.extend_from_slice(&ANC_CDP_ID);
+    data.push(cdp.len() as u8);
+    data.extend_from_slice(cdp);
+    data.push(checksum_8bit(&data));
+    data
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tests::*;
+    use crate::Framerate;
+
+    fn with_parity(data: &[u8]) -> Vec<u16> {
+        data.iter().map(|&d| word(d)).collect()
+    }
+
+    #[test]
+    fn read_anc() {
+        test_init_log();
+        let cdp = build_cdp(0x3, 0x41, 0x1234, &[0x72, 0xe1, 0xFC, 0x94, 0x20]);
+        let mut data = vec![0x61, 0x01, cdp.len() as u8];
+        data.extend_from_slice(&cdp);
+        let mut words = with_parity(&data);
+        words.push(checksum(&words));
+        data.push(checksum_8bit(&data));
+
+        assert_eq!(cdp_from_anc(&words).unwrap(), cdp);
+        let mut with_adf = ADF.to_vec();
+        with_adf.extend_from_slice(&words);
+        assert_eq!(cdp_from_anc(&with_adf).unwrap(), cdp);
+        assert_eq!(cdp_from_anc_8bit(&data).unwrap(), cdp);
+        let mut with_adf = ADF_8BIT.to_vec();
+        with_adf.extend_from_slice(&data);
+        assert_eq!(cdp_from_anc_8bit(&with_adf).unwrap(), cdp);
+
+        let mut parser = CDPParser::new();
+        parse_anc(&mut parser, &words).unwrap();
+        assert_eq!(parser.sequence(), 0x1234);
+        assert!(parser.pop_packet().is_none());
+        assert_eq!(
+            parser.cea608(),
+            Some([cea708_types::Cea608::Field1(0x94, 0x20)].as_slice())
+        );
+        parse_anc_8bit(&mut parser, &data).unwrap();
+        assert_eq!(parser.sequence(), 0x1234);
+    }
+
+    #[test]
+    fn read_anc_invalid() {
+        test_init_log();
+        let cdp = build_cdp(0x3, 0x41, 0x1234, &[0x72, 0xe1, 0xFC, 0x94, 0x20]);
+        let mut data = vec![0x61, 0x01, cdp.len() as u8];
+        data.extend_from_slice(&cdp);
+        let mut words = with_parity(&data);
+        words.push(checksum(&words));
+        data.push(checksum_8bit(&data));
+
+        assert_eq!(cdp_from_anc(&words[..2]), Err(AncError::Truncated));
+        assert_eq!(
+            cdp_from_anc(&words[..words.len() - 1]),
+            Err(AncError::Truncated)
+        );
+        assert_eq!(
+            cdp_from_anc_8bit(&[0x62, 0x01, 0x00]),
+            Err(AncError::NotCdp)
+        );
+
+        let mut corrupted = words.clone();
+        corrupted[5] ^= 0x01;
+        assert!(matches!(
+            cdp_from_anc(&corrupted),
+            Err(AncError::ChecksumMismatch { .. })
+        ));
+        let mut corrupted = data.clone();
+        corrupted[5] ^= 0x01;
+        assert!(matches!(
+            cdp_from_anc_8bit(&corrupted),
+            Err(AncError::ChecksumMismatch { .. })
+        ));
+
+        // the CDP checksum is validated by the parser
+        let mut data = vec![0x61, 0x01, cdp.len() as u8];
+        data.extend_from_slice(&cdp);
+        let last = data.len() - 1;
+        data[last] ^= 0x01;
+        data.push(checksum_8bit(&data));
+        let mut parser = CDPParser::new();
+        assert!(matches!(
+            parse_anc_8bit(&mut parser, &data),
+            Err(AncError::Parser(ParserError::ChecksumFailed { .. }))
+        ));
+    }
+
+    #[test]
+    fn write_read_anc() {
+        test_init_log();
+        let mut writer = crate::CDPWriter::new(Framerate::from_id(0x4).unwrap());
+        writer.set_sequence_count(0x1234);
+        writer.push_cea608(cea708_types::Cea608::Field1(0x94, 0x20));
+        let mut cdp = vec![];
+        writer.write(&mut cdp).unwrap();
+
+        let words = wrap_cdp(&cdp);
+        assert_eq!(words[..3], [0x161, 0x101, word(cdp.len() as u8)]);
+        assert_eq!(cdp_from_anc(&words).unwrap(), cdp);
+        let data = wrap_cdp_8bit(&cdp);
+        assert_eq!(data.len(), cdp.len() + 4);
+        assert_eq!(cdp_from_anc_8bit(&data).unwrap(), cdp);
+        assert_eq!(
+            words.iter().map(|word| *word as u8).collect::<Vec<_>>(),
+            data
+        );
+
+        let mut parser = CDPParser::new();
+        parse_anc(&mut parser, &words).unwrap();
+        assert_eq!(parser.sequence(), 0x1234);
+        assert_eq!(
+            parser.cea608(),
+            Some([cea708_types::Cea608::Field1(0x94, 0x20)].as_slice())
+        );
+    }
+}
@@ -0,0 +1,383 @@
+// Copyright (C) 2023 Matthew Waters <matthew@centricular.com>
+//
+// Licensed under the MIT license <LICENSE-MIT> or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Framerate conversion of a CDP stream
+//!
+//! A [`FramerateConverter`] takes the CDPs of a stream at one [`Framerate`] and produces CDPs at
+//! another [`Framerate`] redistributing the caption data over the output CDPs.
+//!
+//! The following conversions are supported:
+//!
+//! - between identical framerates,
+//! - 50fps to 25fps and 25fps to 50fps,
+//! - 60fps to 30fps and 30fps to 60fps,
+//! - 60000/1001fps to 30000/1001fps and 30000/1001fps to 60000/1001fps.
+
+use std::collections::VecDeque;
+
+use crate::{CDPParser, CDPWriter, Framerate, ParserError, TimeCode, WriterError};
+
+/// Errors that can occur when converting CDPs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConvertError {
+    /// Parsing an input CDP failed
+    Parser(ParserError),
+    /// Writing an output CDP failed
+    Writer(WriterError),
+    /// An input CDP does not have the input framerate of the converter
+    FramerateMismatch,
+}
+
+impl From<ParserError> for ConvertError {
+    fn from(value: ParserError) -> Self {
+        ConvertError::Parser(value)
+    }
+}
+
+impl From<WriterError> for ConvertError {
+    fn from(value: WriterError) -> Self {
+        ConvertError::Writer(value)
+    }
+}
+
+impl std::fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(&format!("{self:?}"))
+    }
+}
+
+/// How many output CDPs are produced for each input CDP
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Ratio {
+    Same,
+    Double,
+    Half,
+}
+
+/// Converts a CDP stream from one [`Framerate`] to another.
+///
+/// The time code of each output CDP is derived from the input time codes.  Above 30fps, time
+/// codes count pairs of frames with the field flag marking the second frame of a pair so halving
+/// the framerate produces an output CDP with the time code of the first frame of each pair once
+/// both frames have been pushed and doubling the framerate produces both frames of the pair.
+/// The sequence count of the output CDPs starts at 0 and is incremented for every output CDP.
+///
+/// Caption data is queued and written into the output CDPs as allowed by the output framerate
+/// (see [`Framerate::max_cc_count`]).  Service information and future sections are not
+/// converted.
+#[derive(Debug)]
+pub struct FramerateConverter {
+    input: Framerate,
+    output: Framerate,
+    ratio: Ratio,
+    parser: CDPParser,
+    writer: CDPWriter,
+    n_input: u64,
+    first_time_code: Option<TimeCode>,
+    pending: VecDeque<Option<TimeCode>>,
+}
+
+impl FramerateConverter {
+    /// Create a new [`FramerateConverter`] from `input` to `output`.  Returns `None` if the
+    /// conversion is not supported.
+    pub fn new(input: Framerate, output: Framerate) -> Option<Self> {
+        let ratio = if input.denom() != output.denom() {
+            return None;
+        } else if input.numer() == output.numer() {
+            Ratio::Same
+        } else if input.numer() * 2 == output.numer() {
+            Ratio::Double
+        } else if input.numer() == output.numer() * 2 {
+            Ratio::Half
+        } else {
+            return None;
+        };
+
+        let mut writer = CDPWriter::new(output);
+        writer.set_auto_sequence(true);
+
+        Some(Self {
+            input,
+            output,
+            ratio,
+            parser: CDPParser::new(),
+            writer,
+            n_input: 0,
+            first_time_code: None,
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// Whether converting from `input` to `output` is supported
+    pub fn is_supported(input: Framerate, output: Framerate) -> bool {
+        Self::new(input, output).is_some()
+    }
+
+    /// The framerate of the input CDPs
+    pub fn input_framerate(&self) -> Framerate {
+        self.input
+    }
+
+    /// The framerate of the output CDPs
+    pub fn output_framerate(&self) -> Framerate {
+        self.output
+    }
+
+    /// Whether to output padding in the output CDPs.  See [`CDPWriter::set_output_padding`].
+    pub fn set_output_padding(&mut self, output_padding: bool) {
+        self.writer.set_output_padding(output_padding);
+    }
+
+    /// Whether padding will be written
+    pub fn output_padding(&self) -> bool {
+        self.writer.output_padding()
+    }
+
+    /// Push the next input CDP.
+    ///
+    /// [`ConvertError::FramerateMismatch`] is returned if the CDP does not have the input
+    /// framerate.  The caption data of a CDP that fails to parse is not converted.
+    pub fn push(&mut self, cdp: &[u8]) -> Result<(), ConvertError> {
+        self.parser.parse(cdp)?;
+        if self.parser.framerate() != Some(self.input) {
+            self.parser.clear_captions();
+            return Err(ConvertError::FramerateMismatch);
+        }
+
+        for packet in self.parser.drain_packets() {
+            self.writer.push_packet(packet);
+        }
+        for cea608 in self.parser.cea608().unwrap_or_default() {
+            self.writer.push_cea608(*cea608);
+        }
+        self.writer
+            .set_caption_service_active(self.parser.caption_service_active());
+
+        let time_code = self.parser.time_code();
+        match self.ratio {
+            Ratio::Same => self.pending.push_back(time_code),
+            Ratio::Double => {
+                for field in 0..2 {
+                    self.pending
+                        .push_back(time_code.map(|time_code| TimeCode { field, ..time_code }));
+                }
+            }
+            Ratio::Half => {
+                if self.n_input.is_multiple_of(2) {
+                    self.first_time_code = time_code;
+                } else {
+                    self.pending
+                        .push_back(self.first_time_code.map(|time_code| TimeCode {
+                            field: 0,
+                            ..time_code
+                        }));
+                }
+            }
+        }
+        self.n_input += 1;
+
+        Ok(())
+    }
+
+    /// The number of output CDPs that can be retrieved with [`FramerateConverter::pop_cdp`]
+    pub fn pending_cdp_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Write the next output CDP or `None` if more input is required
+    pub fn pop_cdp(&mut self) -> Result<Option<Vec<u8>>, ConvertError> {
+        let Some(time_code) = self.pending.pop_front() else {
+            return Ok(None);
+        };
+        self.writer.set_time_code(time_code);
+        let mut cdp = vec![];
+        self.writer.write(&mut cdp)?;
+        Ok(Some(cdp))
+    }
+
+    /// Convert all of the CDPs in `cdps` returning an iterator over the output CDPs.
+    ///
+    /// The iterator ends after the first error.
+    pub fn convert<I>(mut self, cdps: I) -> impl Iterator<Item = Result<Vec<u8>, ConvertError>>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<[u8]>,
+    {
+        let mut cdps = cdps.into_iter();
+        let mut failed = false;
+        std::iter::from_fn(move || {
+            if failed {
+                return None;
+            }
+            loop {
+                match self.pop_cdp() {
+                    Ok(Some(cdp)) => return Some(Ok(cdp)),
+                    Ok(None) => (),
+                    Err(e) => {
+                        failed = true;
+                        return Some(Err(e));
+                    }
+                }
+                let cdp = cdps.next()?;
+                if let Err(e) = self.push(cdp.as_ref()) {
+                    failed = true;
+                    return Some(Err(e));
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tests::*;
+    use cea708_types::Cea608;
+
+    fn input_cdps(
+        framerate: Framerate,
+        time_codes: &[TimeCode],
+        cea608: &[Cea608],
+    ) -> Vec<Vec<u8>> {
+        let mut writer = CDPWriter::new(framerate);
+        writer.set_auto_sequence(true);
+        let per_cdp = cea608.len() / time_codes.len();
+        time_codes
+            .iter()
+            .zip(cea608.chunks(per_cdp))
+            .map(|(time_code, cea608)| {
+                for pair in cea608 {
+                    writer.push_cea608(*pair);
+                }
+                writer.set_time_code(Some(*time_code));
+                let mut cdp = vec![];
+                writer.write(&mut cdp).unwrap();
+                cdp
+            })
+            .collect()
+    }
+
+    fn parse_output(cdps: &[Vec<u8>], framerate: Framerate) -> (Vec<TimeCode>, Vec<Cea608>) {
+        let mut parser = CDPParser::new();
+        let mut time_codes = vec![];
+        let mut cea608 = vec![];
+        for (i, cdp) in cdps.iter().enumerate() {
+            parser.parse(cdp).unwrap();
+            assert_eq!(parser.framerate(), Some(framerate));
+            assert_eq!(parser.sequence(), i as u16);
+            time_codes.push(parser.time_code().unwrap());
+            cea608.extend_from_slice(parser.cea608().unwrap_or_default());
+        }
+        (time_codes, cea608)
+    }
+
+    fn time_code(frames: u8, field: u8) -> TimeCode {
+        TimeCode {
+            hours: 1,
+            minutes: 2,
+            seconds: 3,
+            frames,
+            field,
+            drop_frame: false,
+        }
+    }
+
+    #[test]
+    fn unsupported() {
+        test_init_log();
+        let fps = |id| Framerate::from_id(id).unwrap();
+        assert!(FramerateConverter::is_supported(fps(0x7), fps(0x4)));
+        assert!(FramerateConverter::is_supported(fps(0x4), fps(0x7)));
+        assert!(FramerateConverter::is_supported(fps(0x3), fps(0x6)));
+        assert!(FramerateConverter::is_supported(fps(0x8), fps(0x5)));
+        assert!(FramerateConverter::is_supported(fps(0x2), fps(0x2)));
+        assert!(!FramerateConverter::is_supported(fps(0x2), fps(0x3)));
+        assert!(!FramerateConverter::is_supported(fps(0x5), fps(0x4)));
+        assert!(!FramerateConverter::is_supported(fps(0x8), fps(0x4)));
+    }
+
+    #[test]
+    fn convert_60_to_30() {
+        test_init_log();
+        let input = Framerate::from_id(0x8).unwrap();
+        let output = Framerate::from_id(0x5).unwrap();
+        let time_codes = [
+            time_code(4, 0),
+            time_code(4, 1),
+            time_code(5, 0),
+            time_code(5, 1),
+        ];
+        let cea608 = [
+            Cea608::Field1(0x94, 0x20),
+            Cea608::Field2(0x15, 0x20),
+            Cea608::Field1(0xc1, 0xc2),
+            Cea608::Field2(0x43, 0x44),
+        ];
+        let cdps = input_cdps(input, &time_codes, &cea608);
+
+        let converter = FramerateConverter::new(input, output).unwrap();
+        let converted = converter
+            .convert(cdps.iter())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(converted.len(), 2);
+        let (time_codes, parsed_cea608) = parse_output(&converted, output);
+        assert_eq!(time_codes, [time_code(4, 0), time_code(5, 0)]);
+        assert_eq!(parsed_cea608, cea608);
+    }
+
+    #[test]
+    fn convert_30_to_60() {
+        test_init_log();
+        let input = Framerate::from_id(0x5).unwrap();
+        let output = Framerate::from_id(0x8).unwrap();
+        let time_codes = [time_code(4, 0), time_code(5, 0)];
+        let cea608 = [
+            Cea608::Field1(0x94, 0x20),
+            Cea608::Field2(0x15, 0x20),
+            Cea608::Field1(0xc1, 0xc2),
+            Cea608::Field2(0x43, 0x44),
+        ];
+        let cdps = input_cdps(input, &time_codes, &cea608);
+
+        let mut converter = FramerateConverter::new(input, output).unwrap();
+        let mut converted = vec![];
+        for cdp in cdps.iter() {
+            converter.push(cdp).unwrap();
+            assert_eq!(converter.pending_cdp_count(), 2);
+            while let Some(cdp) = converter.pop_cdp().unwrap() {
+                converted.push(cdp);
+            }
+        }
+        assert_eq!(converted.len(), 4);
+        let (time_codes, parsed_cea608) = parse_output(&converted, output);
+        assert_eq!(
+            time_codes,
+            [
+                time_code(4, 0),
+                time_code(4, 1),
+                time_code(5, 0),
+                time_code(5, 1)
+            ]
+        );
+        assert_eq!(parsed_cea608, cea608);
+    }
+
+    #[test]
+    fn framerate_mismatch() {
+        test_init_log();
+        let input = Framerate::from_id(0x8).unwrap();
+        let output = Framerate::from_id(0x5).unwrap();
+        let cdps = input_cdps(output, &[time_code(4, 0)], &[Cea608::Field1(0x94, 0x20)]);
+        let mut converter = FramerateConverter::new(input, output).unwrap();
+        assert_eq!(
+            converter.push(&cdps[0]),
+            Err(ConvertError::FramerateMismatch)
+        );
+        assert_eq!(converter.pop_cdp(), Ok(None));
+    }
+}
@@ -10,13 +10,131 @@
 //!
 //! The reference for this implementation is the `SMPTE 334-2-2007` specification.
 
+//! ## Logging
+//!
+//! Log messages are emitted using the [`log`](https://docs.rs/log) crate with the following
+//! targets:
+//!
+//! - `cdp_types::parser`: parsing and validating CDPs,
+//! - `cdp_types::mcc`: reading and writing MCC files,
+//! - `cdp_types::scc`: writing SCC files.
+
 #[macro_use]
 extern crate log;
 
+/// The log target of messages about parsing CDPs
+const PARSER_LOG_TARGET: &str = "cdp_types::parser";
+
+/// Fixed values used in the CDP bitstream
+pub mod consts {
+    /// The two magic bytes at the start of every CDP
+    pub const CDP_MAGIC: [u8; 2] = [0x96, 0x69];
+    /// Identifier of the time code section
+    pub const TIME_CODE_ID: u8 = 0x71;
+    /// Identifier of the cc_data section
+    pub const CC_DATA_ID: u8 = 0x72;
+    /// Identifier of the service information section
+    pub const SVC_INFO_ID: u8 = 0x73;
+    /// Identifier of the CDP footer
+    pub const CDP_FOOTER_ID: u8 = 0x74;
+    /// The first identifier reserved for future sections
+    pub const FUTURE_SECTION_ID_START: u8 = 0x75;
+    /// The last identifier reserved for future sections
+    pub const FUTURE_SECTION_ID_END: u8 = 0xEF;
+
+    /// The length of the CDP header: magic, length, framerate, flags and sequence count
+    pub const HEADER_LEN: usize = 7;
+    /// The length of the CDP footer: identifier, sequence count and checksum
+    pub const FOOTER_LEN: usize = 4;
+    /// The length of the smallest possible CDP containing only a header and footer
+    pub const MIN_PACKET_LEN: usize = HEADER_LEN + FOOTER_LEN;
+    /// The length of the time code section including its identifier
+    pub const TIME_CODE_SECTION_LEN: usize = 5;
+    /// The length of the cc_data section identifier and cc_count preceding the cc_data triples
+    pub const CC_DATA_HEADER_LEN: usize = 2;
+    /// The length of the service information section identifier and svc_count preceding the
+    /// service entries
+    pub const SVC_INFO_HEADER_LEN: usize = 2;
+    /// The maximum number of services in a service information section as limited by the 4-bit
+    /// svc_count
+    pub const MAX_SERVICES: usize = 15;
+}
+
+use consts::*;
+
+/// The identifier of a section within a CDP
+///
+/// ```
+/// # use cdp_types::CdpSection;
+/// assert_eq!(CdpSection::try_from(0x72), Ok(CdpSection::CcData));
+/// assert_eq!(CdpSection::try_from(0x80), Ok(CdpSection::Future(0x80)));
+/// assert_eq!(u8::from(CdpSection::Footer), 0x74);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CdpSection {
+    /// The time code section
+    TimeCode,
+    /// The cc_data section
+    CcData,
+    /// The service information section
+    ServiceInfo,
+    /// A section reserved for future use with the contained identifier
+    Future(u8),
+    /// The CDP footer
+    Footer,
+}
+
+/// Fails with [`ParserError::WrongMagic`] for identifiers that are not a valid section
+impl TryFrom<u8> for CdpSection {
+    type Error = ParserError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            TIME_CODE_ID => Ok(CdpSection::TimeCode),
+            CC_DATA_ID => Ok(CdpSection::CcData),
+            SVC_INFO_ID => Ok(CdpSection::ServiceInfo),
+            CDP_FOOTER_ID => Ok(CdpSection::Footer),
+            FUTURE_SECTION_ID_START..=FUTURE_SECTION_ID_END => Ok(CdpSection::Future(value)),
+            _ => Err(ParserError::WrongMagic),
+        }
+    }
+}
+
+impl From<CdpSection> for u8 {
+    fn from(value: CdpSection) -> Self {
+        match value {
+            CdpSection::TimeCode => TIME_CODE_ID,
+            CdpSection::CcData => CC_DATA_ID,
+            CdpSection::ServiceInfo => SVC_INFO_ID,
+            CdpSection::Footer => CDP_FOOTER_ID,
+            CdpSection::Future(id) => id,
+        }
+    }
+}
+
+pub mod anc;
+pub mod convert;
+pub mod mcc;
+pub mod scc;
+
 /// Various possible errors when parsing data
+///
+/// New variants may be added in the future so matches must include a wildcard arm.
+///
+/// ```
+/// # use cdp_types::{CDPParser, ParserError};
+/// let mut parser = CDPParser::new();
+/// match parser.parse(&[0x96, 0x69]) {
+///     Ok(()) => (),
+///     Err(ParserError::WrongMagic) => unreachable!(),
+///     Err(_) => (),
+/// }
+/// ```
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum ParserError {
-    /// The length of the data does not match the length in the data
+    /// The length of the data does not match the length in the data or the data is internally
+    /// inconsistent
     LengthMismatch {
         /// Expected minimum size of the data
         expected: usize,
@@ -30,19 +148,55 @@ pub enum ParserError {
     /// Some 'fixed' bits did not have the correct value
     InvalidFixedBits,
     /// CEA-608 bytes were found after CEA-708 bytes
-    Cea608AfterCea708,
+    Cea608AfterCea708 {
+        /// The offset of the CEA-608 cc_data triple from the first cc_data triple of the cc_data
+        /// section
+        byte_pos: usize,
+    },
     /// Failed to validate the checksum
-    ChecksumFailed,
+    ChecksumFailed {
+        /// The checksum calculated from the data
+        expected: u8,
+        /// The checksum contained in the data
+        actual: u8,
+    },
     /// Sequence count differs between the header and the footer.  Usuall indicates this packet was
     /// spliced together incorrectly.
-    SequenceCountMismatch,
+    SequenceCountMismatch {
+        /// The sequence count in the header
+        header: u16,
+        /// The sequence count in the footer
+        footer: u16,
+    },
+    /// Reading the data failed
+    Io(std::io::ErrorKind),
+    /// CEA-608 field 2 bytes were found without preceding CEA-608 field 1 bytes
+    Cea608FieldOrder,
+    /// Not enough data was provided for a complete CDP
+    Truncated {
+        /// The minimum total size of the data required
+        needed: usize,
+    },
+    /// The values of a time code are out of range for the framerate
+    InvalidTimeCode,
+    /// A service information section does not describe any services
+    EmptyServiceInfo,
+    /// A section indicated by the flags does not start with the expected section identifier
+    BadSectionId {
+        /// The expected section identifier
+        expected: u8,
+        /// The byte found instead
+        found: u8,
+        /// The offset of the byte in the CDP
+        offset: usize,
+    },
 }
 
 impl From<cea708_types::ParserError> for ParserError {
     fn from(value: cea708_types::ParserError) -> Self {
         match value {
-            cea708_types::ParserError::Cea608AfterCea708 { byte_pos: _ } => {
-                ParserError::Cea608AfterCea708
+            cea708_types::ParserError::Cea608AfterCea708 { byte_pos } => {
+                ParserError::Cea608AfterCea708 { byte_pos }
             }
             cea708_types::ParserError::LengthMismatch { expected, actual } => {
                 ParserError::LengthMismatch { expected, actual }
@@ -51,6 +205,12 @@ impl From<cea708_types::ParserError> for ParserError {
     }
 }
 
+impl From<std::io::Error> for ParserError {
+    fn from(value: std::io::Error) -> Self {
+        ParserError::Io(value.kind())
+    }
+}
+
 impl std::fmt::Display for ParserError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.pad(&format!("{self:?}"))
@@ -58,12 +218,341 @@ impl std::fmt::Display for ParserError {
 }
 
 /// An error enum returned when writing data fails
+///
+/// New variants may be added in the future so matches must include a wildcard arm.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum WriterError {
     /// Writing would overflow by how many bytes
     WouldOverflow(usize),
     /// It is not possible to write to this resource
     ReadOnly,
+    /// Writing the data failed
+    Io(std::io::ErrorKind),
+    /// The provided cc_data is not valid
+    InvalidCcData,
+    /// The CDP would be larger than the configured maximum length
+    PacketTooLarge {
+        /// The length of the CDP
+        len: usize,
+        /// The configured maximum length
+        max_len: u8,
+    },
+    /// The section order contains a section that cannot be written or is missing or duplicates a
+    /// section
+    InvalidSectionOrder,
+}
+
+impl From<std::io::Error> for WriterError {
+    fn from(value: std::io::Error) -> Self {
+        WriterError::Io(value.kind())
+    }
+}
+
+impl std::fmt::Display for WriterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(&format!("{self:?}"))
+    }
+}
+
+/// Calculate the checksum byte of a CDP.
+///
+/// `data` should contain all of the bytes of the CDP excluding the final checksum byte.  The sum
+/// of `data` and the returned checksum byte will be `0` modulo 256.
+pub fn cdp_checksum(data: &[u8]) -> u8 {
+    let sum = data.iter().fold(0u8, |sum, d| sum.wrapping_add(*d));
+    // 256 - sum without having to use a type larger than u8
+    (!sum).wrapping_add(1)
+}
+
+/// Check that the last byte of the CDP in `data` is the correct checksum for the preceding bytes.
+///
+/// No other validation of the CDP is performed.  [`ParserError::LengthMismatch`] is returned if
+/// `data` is shorter than the smallest possible CDP.
+pub fn verify_checksum(data: &[u8]) -> Result<(), ParserError> {
+    if data.len() < MIN_PACKET_LEN {
+        return Err(ParserError::LengthMismatch {
+            expected: MIN_PACKET_LEN,
+            actual: data.len(),
+        });
+    }
+    let (checksum, data) = data.split_last().unwrap();
+    let checksum_byte = cdp_checksum(data);
+    trace!(
+        target: PARSER_LOG_TARGET,
+        "calculated checksum {checksum_byte:#x}, checksum in data {checksum:#x}"
+    );
+    if checksum_byte != *checksum {
+        return Err(ParserError::ChecksumFailed {
+            expected: checksum_byte,
+            actual: *checksum,
+        });
+    }
+    Ok(())
+}
+
+/// Recalculate the checksum of the CDP in `data` and store it in the last byte, e.g. after
+/// modifying a captured CDP.
+///
+/// # Panics
+///
+/// Panics if `data` is shorter than the smallest possible CDP or the length of `data` does not
+/// match the length contained in the CDP.
+pub fn fix_checksum(data: &mut [u8]) {
+    assert!(data.len() >= MIN_PACKET_LEN);
+    assert_eq!(data.len(), data[2] as usize);
+    let (checksum, data) = data.split_last_mut().unwrap();
+    *checksum = cdp_checksum(data);
+}
+
+/// The signed difference between the CDP sequence counts `prev` and `next` taking into account
+/// that the sequence count wraps around from `0xffff` to `0x0000`.
+///
+/// The shortest distance is returned so the result is always in the range `-32768..=32767`.  A
+/// difference of `1` indicates that no CDPs are missing between `prev` and `next`.
+pub fn sequence_delta(prev: u16, next: u16) -> i32 {
+    next.wrapping_sub(prev) as i16 as i32
+}
+
+/// Produce a minimal CDP at `framerate` with `sequence` that contains a cc_data section with only
+/// padding, e.g. for keeping a caption stream alive or for seeding test streams.
+pub fn empty_cdp(framerate: Framerate, sequence: u16) -> Vec<u8> {
+    let mut writer = CDPWriter::builder(framerate)
+        .padding(true)
+        .sequence_count(sequence)
+        .build();
+    let mut data = vec![];
+    writer
+        .write(&mut data)
+        .expect("Writing to a Vec cannot fail");
+    data
+}
+
+/// Split `data` containing back to back CDPs into the data of each CDP.
+///
+/// Only the magic and length of each CDP is checked.  Iteration stops after the first error,
+/// including for a trailing incomplete CDP which is reported as [`ParserError::Truncated`].
+pub fn split_cdps(data: &[u8]) -> impl Iterator<Item = Result<&[u8], ParserError>> {
+    let mut remaining = data;
+    std::iter::from_fn(move || {
+        if remaining.is_empty() {
+            return None;
+        }
+        let ret = if remaining.len() < 3 {
+            Err(ParserError::Truncated {
+                needed: MIN_PACKET_LEN,
+            })
+        } else if remaining[..2] != CDP_MAGIC {
+            Err(ParserError::WrongMagic)
+        } else if (remaining[2] as usize) < MIN_PACKET_LEN {
+            Err(ParserError::LengthMismatch {
+                expected: MIN_PACKET_LEN,
+                actual: remaining[2] as usize,
+            })
+        } else if remaining.len() < remaining[2] as usize {
+            Err(ParserError::Truncated {
+                needed: remaining[2] as usize,
+            })
+        } else {
+            let (cdp, rest) = remaining.split_at(remaining[2] as usize);
+            remaining = rest;
+            return Some(Ok(cdp));
+        };
+        remaining = &[];
+        Some(ret)
+    })
+}
+
+/// Produce a human readable description of each section of the CDP in `data`.
+///
+/// Intended for debugging only, the output format is not stable.  Malformed data is described
+/// up to the first inconsistency which is then noted on the last line of the output.
+pub fn debug_dump(data: &[u8]) -> String {
+    use std::fmt::Write;
+
+    fn hex(data: &[u8]) -> String {
+        data.iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn take<'a>(data: &'a [u8], idx: &mut usize, n: usize) -> Result<&'a [u8], String> {
+        if data.len() < *idx + n {
+            return Err(format!(
+                "truncated at offset {idx}, needed {n} bytes but only {} available",
+                data.len() - *idx
+            ));
+        }
+        let ret = &data[*idx..*idx + n];
+        *idx += n;
+        Ok(ret)
+    }
+
+    fn dump(data: &[u8], out: &mut String) -> Result<(), String> {
+        let mut idx = 0;
+
+        let magic = take(data, &mut idx, 2)?;
+        writeln!(out, "magic: {}", hex(magic)).unwrap();
+        if magic != CDP_MAGIC {
+            return Err(format!("wrong magic, expected {}", hex(&CDP_MAGIC)));
+        }
+
+        let len = take(data, &mut idx, 1)?[0] as usize;
+        writeln!(out, "cdp_length: {len}").unwrap();
+        if len != data.len() {
+            return Err(format!(
+                "cdp_length does not match data length {}",
+                data.len()
+            ));
+        }
+
+        let id = take(data, &mut idx, 1)?[0];
+        match Framerate::from_id(id >> 4) {
+            Some(framerate) => writeln!(
+                out,
+                "framerate: {id:#04x} ({}/{})",
+                framerate.numer(),
+                framerate.denom()
+            )
+            .unwrap(),
+            None => writeln!(out, "framerate: {id:#04x} (unknown)").unwrap(),
+        }
+
+        let flags_byte = take(data, &mut idx, 1)?[0];
+        let flags = Flags::from(flags_byte);
+        let names = [
+            (flags.time_code(), "time_code"),
+            (flags.cc_data(), "cc_data"),
+            (flags.svc_info(), "svc_info"),
+            (flags.svc_info_start(), "svc_info_start"),
+            (flags.svc_info_change(), "svc_info_change"),
+            (flags.svc_info_complete(), "svc_info_complete"),
+            (flags.caption_service_active(), "caption_service_active"),
+            (flags.reserved(), "reserved"),
+        ]
+        .iter()
+        .filter_map(|(set, name)| set.then_some(*name))
+        .collect::<Vec<_>>();
+        writeln!(out, "flags: {flags_byte:#04x} ({})", names.join(" ")).unwrap();
+
+        let sequence = take(data, &mut idx, 2)?;
+        let sequence = (sequence[0] as u16) << 8 | sequence[1] as u16;
+        writeln!(out, "sequence: {sequence}").unwrap();
+
+        if flags.time_code() {
+            let section = take(data, &mut idx, 5)?;
+            if section[0] != TIME_CODE_ID {
+                return Err(format!(
+                    "expected time code section, found id {:#04x}",
+                    section[0]
+                ));
+            }
+            match TimeCode::from_wire(section.try_into().unwrap()) {
+                Ok(time_code) => writeln!(
+                    out,
+                    "time_code: {} -> {time_code} field {}",
+                    hex(section),
+                    time_code.field
+                )
+                .unwrap(),
+                Err(err) => return Err(format!("invalid time code {}: {err}", hex(section))),
+            }
+        }
+
+        if flags.cc_data() {
+            let header = take(data, &mut idx, 2)?;
+            if header[0] != CC_DATA_ID {
+                return Err(format!(
+                    "expected cc_data section, found id {:#04x}",
+                    header[0]
+                ));
+            }
+            let cc_count = (header[1] & 0x1f) as usize;
+            writeln!(out, "cc_data: {} -> cc_count {cc_count}", hex(header)).unwrap();
+            if (header[1] & 0xe0) != 0xe0 {
+                return Err(String::from("invalid marker bits in cc_count"));
+            }
+            for triple in take(data, &mut idx, cc_count * 3)?.chunks_exact(3) {
+                writeln!(out, "  {}", hex(triple)).unwrap();
+            }
+        }
+
+        if flags.svc_info() {
+            let header = take(data, &mut idx, 2)?;
+            if header[0] != SVC_INFO_ID {
+                return Err(format!(
+                    "expected svc_info section, found id {:#04x}",
+                    header[0]
+                ));
+            }
+            let svc_count = (header[1] & 0x0f) as usize;
+            writeln!(out, "svc_info: {} -> svc_count {svc_count}", hex(header)).unwrap();
+            for entry in take(data, &mut idx, svc_count * 7)?.chunks_exact(7) {
+                writeln!(out, "  {}", hex(entry)).unwrap();
+            }
+        }
+
+        while take(data, &mut idx, 1)?[0] != CDP_FOOTER_ID {
+            let id = data[idx - 1];
+            if !matches!(CdpSection::try_from(id), Ok(CdpSection::Future(_))) {
+                return Err(format!(
+                    "unknown section id {id:#04x} at offset {}",
+                    idx - 1
+                ));
+            }
+            let len = take(data, &mut idx, 1)?[0] as usize;
+            let section = take(data, &mut idx, len)?;
+            writeln!(out, "future_section {id:#04x}: {}", hex(section)).unwrap();
+        }
+
+        let footer_sequence = take(data, &mut idx, 2)?;
+        let footer_sequence = (footer_sequence[0] as u16) << 8 | footer_sequence[1] as u16;
+        writeln!(out, "footer: sequence {footer_sequence}").unwrap();
+        if footer_sequence != sequence {
+            return Err(String::from(
+                "footer sequence does not match header sequence",
+            ));
+        }
+
+        let checksum = take(data, &mut idx, 1)?[0];
+        let expected = cdp_checksum(&data[..idx - 1]);
+        if checksum != expected {
+            return Err(format!(
+                "checksum {checksum:#04x} does not match calculated {expected:#04x}"
+            ));
+        }
+        writeln!(out, "checksum: {checksum:#04x} (valid)").unwrap();
+
+        Ok(())
+    }
+
+    let mut out = String::new();
+    if let Err(err) = dump(data, &mut out) {
+        writeln!(out, "error: {err}").unwrap();
+    }
+    out
+}
+
+/// Set the top bit of `byte` so that the byte has odd parity as required for CEA-608 data
+///
+/// ```
+/// # use cdp_types::set_odd_parity;
+/// assert_eq!(set_odd_parity(0x14), 0x94);
+/// assert_eq!(set_odd_parity(0x20), 0x20);
+/// ```
+pub fn set_odd_parity(byte: u8) -> u8 {
+    let byte = byte & 0x7f;
+    if byte.count_ones() % 2 == 1 {
+        byte
+    } else {
+        byte | 0x80
+    }
+}
+
+/// Whether `byte` has odd parity as required for CEA-608 data
+pub fn check_odd_parity(byte: u8) -> bool {
+    byte.count_ones() % 2 == 1
 }
 
 static FRAMERATES: [Framerate; 8] = [
@@ -109,7 +598,7 @@ static FRAMERATES: [Framerate; 8] = [
     },
 ];
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Framerate {
     id: u8,
     numer: u32,
@@ -132,8 +621,88 @@ impl Framerate {
     pub fn denom(&self) -> u32 {
         self.denom
     }
+
+    /// The maximum number of cc_data triples in a single CDP at this framerate.
+    ///
+    /// CEA-708 has a maximum bitrate of 9600 bits/s or 600 cc_data triples per second which is
+    /// shared between every CDP, e.g. 20 triples at 30fps and 10 triples at 60fps.
+    pub fn max_cc_count(&self) -> u8 {
+        // 600 * denom / numer rounded to the nearest integer
+        ((1200 * self.denom as u64 + self.numer as u64) / (2 * self.numer as u64)) as u8
+    }
+
+    /// The number of CEA-608 byte pairs in a single CDP at this framerate, 60 * denom / numer
+    /// rounded to the nearest integer as used by [`cea708_types::CCDataWriter`]
+    fn cea608_pairs_per_frame(&self) -> usize {
+        ((120 * self.denom as u64 + self.numer as u64) / (2 * self.numer as u64)) as usize
+    }
+
+    /// Whether time codes at this framerate count pairs of frames with the field flag
+    /// identifying the second frame of the pair.
+    fn time_code_uses_frame_pairs(&self) -> bool {
+        (self.numer as u64).div_ceil(self.denom as u64) > 30
+    }
+
+    /// The number of frames in each second of a time code at this framerate
+    fn time_code_rate(&self) -> u64 {
+        let nominal = (self.numer as u64).div_ceil(self.denom as u64);
+        if self.time_code_uses_frame_pairs() {
+            nominal / 2
+        } else {
+            nominal
+        }
+    }
+
+    /// The number of time code frame numbers that are skipped at the start of every minute
+    /// except every tenth minute.
+    fn dropped_frames_per_minute(&self, drop_frame: bool) -> u64 {
+        if drop_frame && self.denom == 1001 && self.time_code_rate() == 30 {
+            2
+        } else {
+            0
+        }
+    }
+}
+
+impl PartialOrd for Framerate {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// [`Framerate`]s are ordered by their actual rate, e.g. 24000/1001 is less than 24/1.
+impl Ord for Framerate {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.numer as u64 * other.denom as u64)
+            .cmp(&(other.numer as u64 * self.denom as u64))
+            .then(self.id.cmp(&other.id))
+    }
+}
+
+impl From<Framerate> for cea708_types::Framerate {
+    fn from(value: Framerate) -> Self {
+        cea708_types::Framerate::new(value.numer, value.denom)
+    }
+}
+
+/// Fails with [`ParserError::UnknownFramerate`] if the rate does not have a CDP framerate
+/// identifier.
+impl TryFrom<cea708_types::Framerate> for Framerate {
+    type Error = ParserError;
+
+    fn try_from(value: cea708_types::Framerate) -> Result<Self, Self::Error> {
+        FRAMERATES
+            .iter()
+            .find(|f| {
+                f.numer as u64 * value.denom() as u64 == value.numer() as u64 * f.denom as u64
+            })
+            .copied()
+            .ok_or(ParserError::UnknownFramerate)
+    }
 }
 
+/// The flags byte of a CDP header describing which sections are present
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Flags {
     time_code: bool,
     cc_data: bool,
@@ -142,17 +711,121 @@ pub struct Flags {
     svc_info_change: bool,
     svc_info_complete: bool,
     caption_service_active: bool,
-    _reserved: bool,
+    reserved: bool,
+}
+
+impl Default for Flags {
+    fn default() -> Self {
+        Self {
+            time_code: false,
+            cc_data: false,
+            svc_info: false,
+            svc_info_start: false,
+            svc_info_change: false,
+            svc_info_complete: false,
+            caption_service_active: false,
+            reserved: true,
+        }
+    }
 }
 
 impl Flags {
-    const TIME_CODE_PRESENT: u8 = 0x80;
-    const CC_DATA_PRESENT: u8 = 0x40;
-    const SVC_INFO_PRESENT: u8 = 0x20;
-    const SVC_INFO_START: u8 = 0x10;
-    const SVC_INFO_CHANGE: u8 = 0x08;
-    const SVC_INFO_COMPLETE: u8 = 0x04;
-    const CAPTION_SERVICE_ACTIVE: u8 = 0x02;
+    /// Bit set when a time code section is present
+    pub const TIME_CODE_PRESENT: u8 = 0x80;
+    /// Bit set when a cc_data section is present
+    pub const CC_DATA_PRESENT: u8 = 0x40;
+    /// Bit set when a service information section is present
+    pub const SVC_INFO_PRESENT: u8 = 0x20;
+    /// Bit set when the service information section starts a new service description
+    pub const SVC_INFO_START: u8 = 0x10;
+    /// Bit set when the service information section changes the current service description
+    pub const SVC_INFO_CHANGE: u8 = 0x08;
+    /// Bit set when the service information section completes the service description
+    pub const SVC_INFO_COMPLETE: u8 = 0x04;
+    /// Bit set when a caption service is active
+    pub const CAPTION_SERVICE_ACTIVE: u8 = 0x02;
+    /// Reserved bit.  Should always be set
+    pub const RESERVED: u8 = 0x01;
+
+    /// Whether a time code section is present
+    pub fn time_code(&self) -> bool {
+        self.time_code
+    }
+
+    /// Set whether a time code section is present
+    pub fn set_time_code(&mut self, time_code: bool) {
+        self.time_code = time_code;
+    }
+
+    /// Whether a cc_data section is present
+    pub fn cc_data(&self) -> bool {
+        self.cc_data
+    }
+
+    /// Set whether a cc_data section is present
+    pub fn set_cc_data(&mut self, cc_data: bool) {
+        self.cc_data = cc_data;
+    }
+
+    /// Whether a service information section is present
+    pub fn svc_info(&self) -> bool {
+        self.svc_info
+    }
+
+    /// Set whether a service information section is present
+    pub fn set_svc_info(&mut self, svc_info: bool) {
+        self.svc_info = svc_info;
+    }
+
+    /// Whether the service information starts a new service description
+    pub fn svc_info_start(&self) -> bool {
+        self.svc_info_start
+    }
+
+    /// Set whether the service information starts a new service description
+    pub fn set_svc_info_start(&mut self, svc_info_start: bool) {
+        self.svc_info_start = svc_info_start;
+    }
+
+    /// Whether the service information changes the current service description
+    pub fn svc_info_change(&self) -> bool {
+        self.svc_info_change
+    }
+
+    /// Set whether the service information changes the current service description
+    pub fn set_svc_info_change(&mut self, svc_info_change: bool) {
+        self.svc_info_change = svc_info_change;
+    }
+
+    /// Whether the service information completes the service description
+    pub fn svc_info_complete(&self) -> bool {
+        self.svc_info_complete
+    }
+
+    /// Set whether the service information completes the service description
+    pub fn set_svc_info_complete(&mut self, svc_info_complete: bool) {
+        self.svc_info_complete = svc_info_complete;
+    }
+
+    /// Whether a caption service is active
+    pub fn caption_service_active(&self) -> bool {
+        self.caption_service_active
+    }
+
+    /// Set whether a caption service is active
+    pub fn set_caption_service_active(&mut self, caption_service_active: bool) {
+        self.caption_service_active = caption_service_active;
+    }
+
+    /// The value of the reserved bit
+    pub fn reserved(&self) -> bool {
+        self.reserved
+    }
+
+    /// Set the value of the reserved bit.  SMPTE 334-2 requires this to be set.
+    pub fn set_reserved(&mut self, reserved: bool) {
+        self.reserved = reserved;
+    }
 }
 
 impl From<u8> for Flags {
@@ -165,14 +838,14 @@ impl From<u8> for Flags {
             svc_info_change: (value & Self::SVC_INFO_CHANGE) > 0,
             svc_info_complete: (value & Self::SVC_INFO_COMPLETE) > 0,
             caption_service_active: (value & Self::CAPTION_SERVICE_ACTIVE) > 0,
-            _reserved: (value & 0x01) > 0,
+            reserved: (value & Self::RESERVED) > 0,
         }
     }
 }
 
 impl From<Flags> for u8 {
     fn from(value: Flags) -> Self {
-        let mut ret = 0x1;
+        let mut ret = 0x0;
         if value.time_code {
             ret |= Flags::TIME_CODE_PRESENT;
         }
@@ -194,11 +867,24 @@ impl From<Flags> for u8 {
         if value.caption_service_active {
             ret |= Flags::CAPTION_SERVICE_ACTIVE;
         }
+        if value.reserved {
+            ret |= Flags::RESERVED;
+        }
         ret
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The field of an interlaced frame or, above 30fps, the frame of a pair of frames that a
+/// [`TimeCode`] refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TimeCodeField {
+    /// The first field or frame, the field flag is not set
+    First,
+    /// The second field or frame, the field flag is set
+    Second,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct TimeCode {
     hours: u8,
     minutes: u8,
@@ -208,154 +894,675 @@ pub struct TimeCode {
     drop_frame: bool,
 }
 
-#[derive(Debug, Default)]
-pub struct CDPParser {
-    cc_data_parser: cea708_types::CCDataParser,
-    time_code: Option<TimeCode>,
-    framerate: Option<Framerate>,
-    sequence: u16,
-}
+impl TimeCode {
+    /// Create a new [`TimeCode`] for `framerate`.
+    ///
+    /// Drop frame time codes are used for 30000/1001 and 60000/1001 and non drop frame time codes
+    /// for every other framerate.  Drop frame time codes skip frame numbers 0 and 1 at the start
+    /// of every minute except every tenth minute, e.g. `00:00:59;29` is followed by `00:01:00;02`
+    /// but `00:09:59;29` is followed by `00:10:00;00`.  Above 30fps, `frames` counts pairs of
+    /// frames and `field` selects the second frame of the pair.
+    ///
+    /// Returns [`ParserError::InvalidTimeCode`] if any value is out of range for `framerate` or
+    /// the time code is one of the skipped drop frame time codes.
+    pub fn for_framerate(
+        hours: u8,
+        minutes: u8,
+        seconds: u8,
+        frames: u8,
+        field: bool,
+        framerate: Framerate,
+    ) -> Result<Self, ParserError> {
+        let time_code = Self {
+            hours,
+            minutes,
+            seconds,
+            frames,
+            field: field as u8,
+            drop_frame: framerate.dropped_frames_per_minute(true) > 0,
+        };
+        if !time_code.is_valid_for(framerate) {
+            return Err(ParserError::InvalidTimeCode);
+        }
+        Ok(time_code)
+    }
 
-impl CDPParser {
-    const MIN_PACKET_LEN: usize = 11;
-    const TIME_CODE_ID: u8 = 0x71;
-    const CC_DATA_ID: u8 = 0x72;
-    const SVC_INFO_ID: u8 = 0x73;
-    const CDP_FOOTER_ID: u8 = 0x74;
+    /// Whether all of the values are in range for `framerate` and, for drop frame time codes,
+    /// that `framerate` supports drop frame time codes and this is not one of the skipped time
+    /// codes.
+    fn is_valid_for(&self, framerate: Framerate) -> bool {
+        if self.hours > 23
+            || self.minutes > 59
+            || self.seconds > 59
+            || self.frames as u64 >= framerate.time_code_rate()
+        {
+            return false;
+        }
+        if self.drop_frame {
+            let dropped = framerate.dropped_frames_per_minute(true);
+            if dropped == 0 {
+                return false;
+            }
+            if self.seconds == 0
+                && !self.minutes.is_multiple_of(10)
+                && (self.frames as u64) < dropped
+            {
+                return false;
+            }
+        }
+        true
+    }
 
-    /// Create a new [CDPParser]
-    pub fn new() -> Self {
-        Self::default()
+    /// Analyze this [`TimeCode`] at `framerate` returning the frame number, the elapsed time and
+    /// whether the time code is valid for `framerate`.
+    ///
+    /// ```
+    /// # use cdp_types::{Framerate, TimeCode};
+    /// let framerate = Framerate::from_id(0x4).unwrap();
+    /// let time_code = TimeCode::for_framerate(0, 1, 0, 2, false, framerate).unwrap();
+    /// let analysis = time_code.analyze(framerate);
+    /// assert_eq!(analysis.frame_number(), 1800);
+    /// assert_eq!(analysis.duration().as_millis(), 60060);
+    /// assert!(analysis.is_valid());
+    /// // 25fps does not have drop frame time codes
+    /// assert!(!time_code.analyze(Framerate::from_id(0x3).unwrap()).is_valid());
+    /// ```
+    pub fn analyze(&self, framerate: Framerate) -> TimeCodeAnalysis {
+        TimeCodeAnalysis {
+            frame_number: self.frame_number(framerate),
+            duration: self.to_duration(framerate),
+            valid: self.is_valid_for(framerate),
+        }
     }
 
-    /// Push a complete `CDP` packet into the parser for processing.
-    pub fn parse(&mut self, data: &[u8]) -> Result<(), ParserError> {
-        self.time_code = None;
-        self.framerate = None;
-        self.sequence = 0;
+    /// The hours of this [`TimeCode`]
+    pub fn hours(&self) -> u8 {
+        self.hours
+    }
 
-        trace!("parsing {data:?}");
+    /// The minutes of this [`TimeCode`]
+    pub fn minutes(&self) -> u8 {
+        self.minutes
+    }
 
-        if data.len() < Self::MIN_PACKET_LEN {
-            return Err(ParserError::LengthMismatch {
-                expected: Self::MIN_PACKET_LEN,
-                actual: data.len(),
-            });
-        }
+    /// The seconds of this [`TimeCode`]
+    pub fn seconds(&self) -> u8 {
+        self.seconds
+    }
 
-        if (data[0], data[1]) != (0x96, 0x69) {
-            return Err(ParserError::WrongMagic);
+    /// The frames of this [`TimeCode`]
+    pub fn frames(&self) -> u8 {
+        self.frames
+    }
+
+    /// Whether the field flag of this [`TimeCode`] is set.  See [`TimeCode::tc_field`].
+    pub fn field(&self) -> bool {
+        self.field != 0
+    }
+
+    /// Which field or, above 30fps, which frame of a pair of frames this [`TimeCode`] refers to.
+    /// An unset field flag is the [`TimeCodeField::First`] and a set field flag the
+    /// [`TimeCodeField::Second`].
+    ///
+    /// ```
+    /// # use cdp_types::{Framerate, TimeCode, TimeCodeField};
+    /// let framerate = Framerate::from_id(0x8).unwrap();
+    /// let first = TimeCode::for_framerate(1, 2, 3, 4, false, framerate).unwrap();
+    /// assert_eq!(first.tc_field(), TimeCodeField::First);
+    /// let second = TimeCode::for_framerate(1, 2, 3, 4, true, framerate).unwrap();
+    /// assert!(second.field());
+    /// assert_eq!(second.tc_field(), TimeCodeField::Second);
+    /// ```
+    pub fn tc_field(&self) -> TimeCodeField {
+        if self.field() {
+            TimeCodeField::Second
+        } else {
+            TimeCodeField::First
         }
+    }
 
-        let len = data[2] as usize;
-        if data.len() != len {
-            return Err(ParserError::LengthMismatch {
-                expected: len,
-                actual: data.len(),
+    /// Whether this is a drop frame [`TimeCode`]
+    pub fn drop_frame(&self) -> bool {
+        self.drop_frame
+    }
+
+    /// Parse a complete time code section including the section identifier.
+    ///
+    /// Returns [`ParserError::BadSectionId`] if the section identifier is not the time code
+    /// identifier and [`ParserError::InvalidFixedBits`] if any of the fixed bits are wrong.
+    ///
+    /// ```
+    /// # use cdp_types::TimeCode;
+    /// let time_code = TimeCode::from_wire([0x71, 0xc1, 0x82, 0x83, 0x84]).unwrap();
+    /// assert_eq!(time_code.to_string(), "01:02:03;04");
+    /// assert!(time_code.field());
+    /// assert_eq!(time_code.to_wire(), [0x71, 0xc1, 0x82, 0x83, 0x84]);
+    /// ```
+    pub fn from_wire(data: [u8; TIME_CODE_SECTION_LEN]) -> Result<Self, ParserError> {
+        if data[0] != TIME_CODE_ID {
+            return Err(ParserError::BadSectionId {
+                expected: TIME_CODE_ID,
+                found: data[0],
+                offset: 0,
             });
         }
 
-        let framerate =
-            Framerate::from_id((data[3] & 0xf0) >> 4).ok_or(ParserError::UnknownFramerate)?;
-
-        let flags: Flags = data[4].into();
+        if (data[1] & 0xc0) != 0xc0 {
+            return Err(ParserError::InvalidFixedBits);
+        }
+        let hours = ((data[1] & 0x30) >> 4) * 10 + (data[1] & 0x0f);
 
-        let sequence_count = (data[5] as u16) << 8 | data[6] as u16;
+        if (data[2] & 0x80) != 0x80 {
+            return Err(ParserError::InvalidFixedBits);
+        }
+        let minutes = ((data[2] & 0x70) >> 4) * 10 + (data[2] & 0x0f);
 
-        let mut idx = 7;
-        let time_code = if flags.time_code {
-            trace!("attempting to parse time code");
-            if data.len() < idx + 5 {
-                return Err(ParserError::LengthMismatch {
-                    expected: idx + 5,
-                    actual: data.len(),
-                });
-            }
-            if data[idx] != Self::TIME_CODE_ID {
-                return Err(ParserError::WrongMagic);
-            }
+        let field = (data[3] & 0x80) >> 7;
+        let seconds = ((data[3] & 0x70) >> 4) * 10 + (data[3] & 0x0f);
 
-            idx += 1;
-            if (data[idx] & 0xc0) != 0xc0 {
-                return Err(ParserError::InvalidFixedBits);
-            }
-            let hours = ((data[idx] & 0x30) >> 4) * 10 + (data[idx] & 0x0f);
+        let drop_frame = (data[4] & 0x80) > 0;
+        if (data[4] & 0x40) != 0x00 {
+            return Err(ParserError::InvalidFixedBits);
+        }
+        let frames = ((data[4] & 0x30) >> 4) * 10 + (data[4] & 0x0f);
 
-            idx += 1;
-            if (data[idx] & 0x80) != 0x80 {
-                return Err(ParserError::InvalidFixedBits);
-            }
-            let minutes = ((data[idx] & 0x70) >> 4) * 10 + (data[idx] & 0x0f);
+        Ok(Self {
+            hours,
+            minutes,
+            seconds,
+            frames,
+            field,
+            drop_frame,
+        })
+    }
 
-            idx += 1;
-            let field = (data[idx] & 0x80) >> 7;
-            let seconds = ((data[idx] & 0x70) >> 4) * 10 + (data[idx] & 0x0f);
+    /// The complete time code section including the section identifier as written in a CDP
+    pub fn to_wire(&self) -> [u8; TIME_CODE_SECTION_LEN] {
+        [
+            TIME_CODE_ID,
+            0xc0 | ((self.hours / 10) << 4) | (self.hours % 10),
+            0x80 | ((self.minutes / 10) << 4) | (self.minutes % 10),
+            ((self.field & 0x1) << 7) | ((self.seconds / 10) << 4) | (self.seconds % 10),
+            if self.drop_frame { 0x80 } else { 0x0 }
+                | ((self.frames / 10) << 4)
+                | (self.frames % 10),
+        ]
+    }
 
-            idx += 1;
-            let drop_frame = (data[idx] & 0x80) > 0;
-            if (data[idx] & 0x40) != 0x00 {
-                return Err(ParserError::InvalidFixedBits);
-            }
-            let frames = ((data[idx] & 0x30) >> 4) * 10 + (data[idx] & 0x0f);
+    /// The number of frames from `00:00:00:00` to this time code
+    fn frame_number(&self, framerate: Framerate) -> u64 {
+        let tc_rate = framerate.time_code_rate();
+        let total_minutes = self.hours as u64 * 60 + self.minutes as u64;
+        let tc_frames = (total_minutes * 60 + self.seconds as u64) * tc_rate + self.frames as u64;
+        let dropped = framerate.dropped_frames_per_minute(self.drop_frame)
+            * (total_minutes - total_minutes / 10);
+        let tc_frames = tc_frames.saturating_sub(dropped);
+        if framerate.time_code_uses_frame_pairs() {
+            tc_frames * 2 + (self.field & 0x1) as u64
+        } else {
+            tc_frames
+        }
+    }
 
-            idx += 1;
-            Some(TimeCode {
-                hours,
-                minutes,
-                seconds,
-                frames,
-                field,
-                drop_frame,
-            })
+    /// The [`TimeCode`] for `frame_number` frames from `00:00:00:00`.  Wraps after 24 hours.
+    fn from_frame_number(frame_number: u64, framerate: Framerate, drop_frame: bool) -> Self {
+        let tc_rate = framerate.time_code_rate();
+        let (mut tc_frames, field) = if framerate.time_code_uses_frame_pairs() {
+            (frame_number / 2, (frame_number % 2) as u8)
         } else {
-            None
+            (frame_number, 0)
         };
 
-        let cc_data = if flags.cc_data {
-            trace!("attempting to parse cc_data");
-            if data.len() < idx + 2 {
+        let drop = framerate.dropped_frames_per_minute(drop_frame);
+        if drop > 0 {
+            let frames_per_minute = tc_rate * 60 - drop;
+            let frames_per_10_minutes = tc_rate * 600 - drop * 9;
+            let tens = tc_frames / frames_per_10_minutes;
+            let rem = tc_frames % frames_per_10_minutes;
+            tc_frames += drop * 9 * tens;
+            if rem > drop {
+                tc_frames += drop * ((rem - drop) / frames_per_minute);
+            }
+        }
+
+        Self {
+            hours: ((tc_frames / (tc_rate * 3600)) % 24) as u8,
+            minutes: ((tc_frames / (tc_rate * 60)) % 60) as u8,
+            seconds: ((tc_frames / tc_rate) % 60) as u8,
+            frames: (tc_frames % tc_rate) as u8,
+            field,
+            drop_frame,
+        }
+    }
+
+    /// The amount of real time from `00:00:00:00` to this [`TimeCode`] at `framerate`.
+    ///
+    /// Drop frame time codes (only applicable to 30000/1001 and 60000/1001) skip frame numbers
+    /// so that the time code tracks real time.  Above 30fps, time codes count pairs of frames and
+    /// the field flag selects the second frame of the pair.
+    pub fn to_duration(&self, framerate: Framerate) -> std::time::Duration {
+        let nanos =
+            (self.frame_number(framerate) as u128 * framerate.denom() as u128 * 1_000_000_000
+                + framerate.numer() as u128 / 2)
+                / framerate.numer() as u128;
+        std::time::Duration::from_nanos(nanos as u64)
+    }
+
+    /// The [`TimeCode`] of the frame nearest to `duration` from `00:00:00:00` at `framerate`.
+    ///
+    /// The returned [`TimeCode`] wraps after 24 hours.  See [`TimeCode::to_duration`] for how
+    /// drop frame time codes are handled.
+    pub fn from_duration(
+        duration: std::time::Duration,
+        framerate: Framerate,
+        drop_frame: bool,
+    ) -> Self {
+        let denom = framerate.denom() as u128 * 1_000_000_000;
+        let frame_number = (duration.as_nanos() * framerate.numer() as u128 + denom / 2) / denom;
+        Self::from_frame_number(frame_number as u64, framerate, drop_frame)
+    }
+}
+
+impl PartialOrd for TimeCode {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// [`TimeCode`]s are ordered chronologically by hours, minutes, seconds, frames and then field.
+/// Time codes that only differ in whether they are drop frame are ordered non drop frame first.
+impl Ord for TimeCode {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (
+            self.hours,
+            self.minutes,
+            self.seconds,
+            self.frames,
+            self.field,
+        )
+            .cmp(&(
+                other.hours,
+                other.minutes,
+                other.seconds,
+                other.frames,
+                other.field,
+            ))
+            .then(self.drop_frame.cmp(&other.drop_frame))
+    }
+}
+
+/// Formats as `HH:MM:SS:FF`, or `HH:MM:SS;FF` for drop frame time codes
+impl std::fmt::Display for TimeCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sep = if self.drop_frame { ';' } else { ':' };
+        write!(
+            f,
+            "{:02}:{:02}:{:02}{sep}{:02}",
+            self.hours, self.minutes, self.seconds, self.frames
+        )
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Framerate {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        u.choose(&FRAMERATES).copied()
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Flags {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Flags::from(u8::arbitrary(u)?))
+    }
+}
+
+/// Only generates values that can be represented in a CDP time code section
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for TimeCode {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(TimeCode {
+            hours: u.int_in_range(0..=23)?,
+            minutes: u.int_in_range(0..=59)?,
+            seconds: u.int_in_range(0..=59)?,
+            frames: u.int_in_range(0..=29)?,
+            field: u.int_in_range(0..=1)?,
+            drop_frame: bool::arbitrary(u)?,
+        })
+    }
+}
+
+/// The values of a `CDP` that passed all structural checks
+#[derive(Debug)]
+struct ParsedCdp {
+    framerate: Option<Framerate>,
+    flags: Flags,
+    sequence: u16,
+    time_code: Option<TimeCode>,
+    cc_data: Option<Vec<u8>>,
+}
+
+/// Counters of the results of [`CDPParser::parse`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ParserStats {
+    parsed: u64,
+    checksum_failures: u64,
+    sequence_mismatches: u64,
+    length_mismatches: u64,
+    other_errors: u64,
+}
+
+impl ParserStats {
+    fn update(&mut self, result: &Result<(), ParserError>) {
+        match result {
+            Ok(()) => self.parsed += 1,
+            Err(ParserError::ChecksumFailed { .. }) => self.checksum_failures += 1,
+            Err(ParserError::SequenceCountMismatch { .. }) => self.sequence_mismatches += 1,
+            Err(ParserError::LengthMismatch { .. } | ParserError::Truncated { .. }) => {
+                self.length_mismatches += 1
+            }
+            Err(_) => self.other_errors += 1,
+        }
+    }
+
+    /// The number of CDPs that were parsed successfully
+    pub fn parsed(&self) -> u64 {
+        self.parsed
+    }
+
+    /// The number of CDPs that failed with [`ParserError::ChecksumFailed`]
+    pub fn checksum_failures(&self) -> u64 {
+        self.checksum_failures
+    }
+
+    /// The number of CDPs that failed with [`ParserError::SequenceCountMismatch`]
+    pub fn sequence_mismatches(&self) -> u64 {
+        self.sequence_mismatches
+    }
+
+    /// The number of CDPs that failed with [`ParserError::LengthMismatch`] or
+    /// [`ParserError::Truncated`]
+    pub fn length_mismatches(&self) -> u64 {
+        self.length_mismatches
+    }
+
+    /// The number of CDPs that failed with any other error
+    pub fn other_errors(&self) -> u64 {
+        self.other_errors
+    }
+
+    /// The total number of CDPs that failed to parse
+    pub fn errors(&self) -> u64 {
+        self.checksum_failures
+            + self.sequence_mismatches
+            + self.length_mismatches
+            + self.other_errors
+    }
+}
+
+/// Configuration of the checks performed by a [`CDPParser`]
+#[derive(Debug, Default, Clone, Copy)]
+struct ParseOptions {
+    strict_cea608: bool,
+    recover_on_error: bool,
+    allow_unknown_framerate: bool,
+    strict_svc_info: bool,
+    allow_trailing: bool,
+    trust_cdp_len: bool,
+    recover_truncated_ccdata: bool,
+}
+
+/// The result of [`TimeCode::analyze`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimeCodeAnalysis {
+    frame_number: u64,
+    duration: std::time::Duration,
+    valid: bool,
+}
+
+impl TimeCodeAnalysis {
+    /// The number of frames from `00:00:00:00`.  See [`TimeCode::to_duration`] for how drop frame
+    /// time codes and time codes above 30fps are handled.
+    pub fn frame_number(&self) -> u64 {
+        self.frame_number
+    }
+
+    /// The amount of real time from `00:00:00:00`
+    pub fn duration(&self) -> std::time::Duration {
+        self.duration
+    }
+
+    /// Whether the time code is valid for the framerate.  Out of range values, drop frame time
+    /// codes for framerates without drop frame time codes and skipped drop frame time codes are
+    /// not valid.
+    pub fn is_valid(&self) -> bool {
+        self.valid
+    }
+}
+
+/// The CDP level values of the last CDP parsed by a [`CDPParser`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CDPParserSnapshot {
+    framerate: Option<Framerate>,
+    sequence: u16,
+    time_code: Option<TimeCode>,
+    flags: Flags,
+}
+
+impl CDPParserSnapshot {
+    /// See [`CDPParser::framerate`]
+    pub fn framerate(&self) -> Option<Framerate> {
+        self.framerate
+    }
+
+    /// See [`CDPParser::sequence`]
+    pub fn sequence(&self) -> u16 {
+        self.sequence
+    }
+
+    /// See [`CDPParser::time_code`]
+    pub fn time_code(&self) -> Option<TimeCode> {
+        self.time_code
+    }
+
+    /// See [`CDPParser::sections_present`]
+    pub fn flags(&self) -> Flags {
+        self.flags
+    }
+}
+
+#[derive(Debug)]
+pub struct CDPParser {
+    cc_data_parser: cea708_types::CCDataParser,
+    packets: std::collections::VecDeque<cea708_types::DTVCCPacket>,
+    cea608: Vec<cea708_types::Cea608>,
+    has_caption_data: bool,
+    cc_data_padding_only: bool,
+    time_code: Option<TimeCode>,
+    framerate: Option<Framerate>,
+    sequence: u16,
+    flags: Flags,
+    cc_data: Option<Vec<u8>>,
+    options: ParseOptions,
+    stats: ParserStats,
+}
+
+impl Default for CDPParser {
+    fn default() -> Self {
+        let mut cc_data_parser = cea708_types::CCDataParser::new();
+        cc_data_parser.handle_cea608();
+        Self {
+            cc_data_parser,
+            packets: std::collections::VecDeque::new(),
+            cea608: Vec::new(),
+            has_caption_data: false,
+            cc_data_padding_only: false,
+            time_code: None,
+            framerate: None,
+            sequence: 0,
+            flags: Flags::default(),
+            cc_data: None,
+            options: ParseOptions::default(),
+            stats: ParserStats::default(),
+        }
+    }
+}
+
+impl CDPParser {
+    /// Create a new [CDPParser]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check the structure and checksum of the `CDP` in `data` without modifying any state
+    fn parse_cdp(data: &[u8], options: ParseOptions) -> Result<ParsedCdp, ParserError> {
+        trace!(target: PARSER_LOG_TARGET, "parsing {data:?}");
+
+        if data.len() < 3 {
+            return Err(ParserError::Truncated {
+                needed: MIN_PACKET_LEN,
+            });
+        }
+
+        if data[..2] != CDP_MAGIC {
+            return Err(ParserError::WrongMagic);
+        }
+
+        let len = data[2] as usize;
+        if len < MIN_PACKET_LEN {
+            return Err(ParserError::LengthMismatch {
+                expected: MIN_PACKET_LEN,
+                actual: len,
+            });
+        }
+        if data.len() < len {
+            return Err(ParserError::Truncated { needed: len });
+        }
+        if data.len() != len {
+            if !options.allow_trailing {
+                return Err(ParserError::LengthMismatch {
+                    expected: len,
+                    actual: data.len(),
+                });
+            }
+            trace!(
+                target: PARSER_LOG_TARGET,
+                "ignoring {} trailing bytes",
+                data.len() - len
+            );
+        }
+        let data = &data[..len];
+
+        let framerate_id = (data[3] & 0xf0) >> 4;
+        let framerate = Framerate::from_id(framerate_id);
+        if framerate.is_none() {
+            if !options.allow_unknown_framerate {
+                return Err(ParserError::UnknownFramerate);
+            }
+            debug!(target: PARSER_LOG_TARGET, "ignoring unknown framerate id {framerate_id:#x}");
+        }
+
+        let flags: Flags = data[4].into();
+
+        let sequence_count = (data[5] as u16) << 8 | data[6] as u16;
+
+        let mut idx = 7;
+        let time_code = if flags.time_code {
+            trace!(target: PARSER_LOG_TARGET, "attempting to parse time code");
+            if data.len() < idx + 5 {
+                return Err(ParserError::LengthMismatch {
+                    expected: idx + 5,
+                    actual: data.len(),
+                });
+            }
+            if data[idx] != TIME_CODE_ID {
+                return Err(ParserError::BadSectionId {
+                    expected: TIME_CODE_ID,
+                    found: data[idx],
+                    offset: idx,
+                });
+            }
+
+            let time_code =
+                TimeCode::from_wire(data[idx..idx + TIME_CODE_SECTION_LEN].try_into().unwrap())?;
+            idx += TIME_CODE_SECTION_LEN;
+            Some(time_code)
+        } else {
+            None
+        };
+
+        let cc_data = if flags.cc_data {
+            trace!(target: PARSER_LOG_TARGET, "attempting to parse cc_data");
+            if data.len() < idx + 2 {
                 return Err(ParserError::LengthMismatch {
                     expected: idx + 2,
                     actual: data.len(),
                 });
             }
-            if data[idx] != Self::CC_DATA_ID {
-                return Err(ParserError::WrongMagic);
+            if data[idx] != CC_DATA_ID {
+                return Err(ParserError::BadSectionId {
+                    expected: CC_DATA_ID,
+                    found: data[idx],
+                    offset: idx,
+                });
             }
             idx += 1;
 
             if (data[idx] & 0xe0) != 0xe0 {
                 return Err(ParserError::InvalidFixedBits);
             }
-            let cc_count = (data[idx] & 0x1f) as usize;
+            let mut cc_count = (data[idx] & 0x1f) as usize;
             idx += 1;
+            let footer_idx = data.len() - FOOTER_LEN;
+            if options.recover_truncated_ccdata && footer_idx < idx + cc_count * 3 {
+                let available = footer_idx.saturating_sub(idx) / 3;
+                warn!(
+                    target: PARSER_LOG_TARGET,
+                    "cc_count {cc_count} exceeds the available data, parsing {available} triples"
+                );
+                cc_count = available;
+            }
             if data.len() < idx + cc_count * 3 {
                 return Err(ParserError::LengthMismatch {
                     expected: idx + cc_count * 3,
                     actual: data.len(),
                 });
             }
+            if options.strict_cea608 {
+                Self::check_cea608_field_order(&data[idx..idx + cc_count * 3])?;
+            }
             let mut cc_data = vec![0x80 | 0x40 | cc_count as u8, 0xFF];
             cc_data.extend_from_slice(&data[idx..idx + cc_count * 3]);
             idx += cc_count * 3;
+            if options.recover_truncated_ccdata && footer_idx < idx + 3 && idx < footer_idx {
+                // skip any partial triple
+                idx = footer_idx;
+            }
             Some(cc_data)
         } else {
             None
         };
 
         if flags.svc_info {
-            trace!("attempting to parse svc info");
+            trace!(target: PARSER_LOG_TARGET, "attempting to parse svc info");
             if data.len() < idx + 2 {
                 return Err(ParserError::LengthMismatch {
                     expected: idx + 2,
                     actual: data.len(),
                 });
             }
-            if data[idx] != Self::SVC_INFO_ID {
-                return Err(ParserError::WrongMagic);
+            if data[idx] != SVC_INFO_ID {
+                return Err(ParserError::BadSectionId {
+                    expected: SVC_INFO_ID,
+                    found: data[idx],
+                    offset: idx,
+                });
             }
             idx += 1;
             let svc_count = data[idx] & 0x0f;
+            if svc_count == 0 && options.strict_svc_info {
+                return Err(ParserError::EmptyServiceInfo);
+            }
             idx += 1;
             if data.len() < idx + 7 * svc_count as usize {
                 return Err(ParserError::LengthMismatch {
@@ -367,6 +1574,18 @@ impl CDPParser {
             idx += 7 * svc_count as usize;
         }
 
+        if options.trust_cdp_len {
+            let footer_idx = data.len() - FOOTER_LEN;
+            if idx < footer_idx {
+                trace!(
+                    target: PARSER_LOG_TARGET,
+                    "skipping {} bytes before the footer",
+                    footer_idx - idx
+                );
+                idx = footer_idx;
+            }
+        }
+
         if data.len() < idx + 2 {
             return Err(ParserError::LengthMismatch {
                 expected: idx + 2,
@@ -375,9 +1594,22 @@ impl CDPParser {
         }
 
         // future section handling
-        while data[idx] != Self::CDP_FOOTER_ID {
-            trace!("attempting to parse future section");
-            if data[idx] < 0x75 || data[idx] > 0xEF {
+        while data[idx] != CDP_FOOTER_ID {
+            trace!(target: PARSER_LOG_TARGET, "attempting to parse future section");
+            if !matches!(CdpSection::try_from(data[idx]), Ok(CdpSection::Future(_))) {
+                let footer_idx = data.len() - FOOTER_LEN;
+                if options.recover_on_error
+                    && idx <= footer_idx
+                    && data[footer_idx] == CDP_FOOTER_ID
+                {
+                    warn!(
+                        target: PARSER_LOG_TARGET,
+                        "unknown section id {:#x} at offset {idx}, skipping to the footer",
+                        data[idx]
+                    );
+                    idx = footer_idx;
+                    break;
+                }
                 return Err(ParserError::WrongMagic);
             }
             idx += 1;
@@ -400,256 +1632,1265 @@ impl CDPParser {
         }
 
         // handle cdp footer
-        trace!("attempting to parse footer");
+        trace!(target: PARSER_LOG_TARGET, "attempting to parse footer");
         if data.len() < idx + 4 {
             return Err(ParserError::LengthMismatch {
                 expected: idx + 4,
                 actual: data.len(),
             });
         }
-        if data[idx] != Self::CDP_FOOTER_ID {
+        if data[idx] != CDP_FOOTER_ID {
             return Err(ParserError::WrongMagic);
         }
         idx += 1;
         let footer_sequence_count = (data[idx] as u16) << 8 | data[idx + 1] as u16;
         if sequence_count != footer_sequence_count {
-            return Err(ParserError::SequenceCountMismatch);
+            return Err(ParserError::SequenceCountMismatch {
+                header: sequence_count,
+                footer: footer_sequence_count,
+            });
         }
-        idx += 2;
+        verify_checksum(data)?;
 
-        let mut checksum: u8 = 0;
-        for d in data[..data.len() - 1].iter() {
-            checksum = checksum.wrapping_add(*d);
-        }
-        // 256 - checksum without having to use a type larger than u8
-        let checksum_byte = (!checksum).wrapping_add(1);
-        trace!(
-            "calculate checksum {checksum_byte:#x}, checksum in data {:#x}",
-            data[idx]
-        );
-        if checksum_byte != data[idx] {
-            return Err(ParserError::ChecksumFailed);
+        Ok(ParsedCdp {
+            framerate,
+            flags,
+            sequence: sequence_count,
+            time_code,
+            cc_data,
+        })
+    }
+
+    /// Check whether `data` contains a single well formed `CDP` without parsing it into a
+    /// [`CDPParser`].
+    ///
+    /// All of the checks performed by [`CDPParser::parse`] with the default configuration are
+    /// performed, including those on the contents of the cc_data section.
+    pub fn validate(data: &[u8]) -> Result<(), ParserError> {
+        let cdp = Self::parse_cdp(data, ParseOptions::default())?;
+        if let Some(cc_data) = cdp.cc_data {
+            cea708_types::CCDataParser::new().push(&cc_data)?;
         }
+        Ok(())
+    }
+
+    /// Push a complete `CDP` packet into the parser for processing.
+    ///
+    /// If parsing fails, the values from the last successfully parsed `CDP` are kept.
+    pub fn parse(&mut self, data: &[u8]) -> Result<(), ParserError> {
+        let ret = self.parse_inner(data);
+        self.stats.update(&ret);
+        ret
+    }
 
-        if let Some(cc_data) = cc_data {
-            self.cc_data_parser.push(&cc_data)?;
+    fn parse_inner(&mut self, data: &[u8]) -> Result<(), ParserError> {
+        let ParsedCdp {
+            framerate,
+            flags,
+            sequence: sequence_count,
+            time_code,
+            cc_data,
+        } = Self::parse_cdp(data, self.options)?;
+
+        let mut has_caption_data = false;
+        if let Some(ref cc_data) = cc_data {
+            self.cc_data_parser.push(cc_data)?;
+            while let Some(packet) = self.cc_data_parser.pop_packet() {
+                has_caption_data |= !packet.services().is_empty();
+                self.packets.push_back(packet);
+            }
+        }
+        self.cea608.clear();
+        if let Some(cea608) = cc_data.as_ref().and(self.cc_data_parser.cea608()) {
+            self.cea608.extend_from_slice(cea608);
         }
-        self.framerate = Some(framerate);
+        if self.options.strict_cea608 {
+            for pair in self.cea608.iter() {
+                let (cea708_types::Cea608::Field1(byte0, byte1)
+                | cea708_types::Cea608::Field2(byte0, byte1)) = pair;
+                if !check_odd_parity(*byte0) || !check_odd_parity(*byte1) {
+                    warn!(
+                        target: PARSER_LOG_TARGET,
+                        "CEA-608 byte pair {pair:?} does not have odd parity"
+                    );
+                }
+            }
+        }
+        has_caption_data |= self.cea608.iter().any(|pair| {
+            let (byte0, byte1) = match pair {
+                cea708_types::Cea608::Field1(byte0, byte1)
+                | cea708_types::Cea608::Field2(byte0, byte1) => (byte0, byte1),
+            };
+            (byte0 & 0x7f) != 0 || (byte1 & 0x7f) != 0
+        });
+        self.has_caption_data = has_caption_data;
+        self.cc_data_padding_only = cc_data.is_some() && !has_caption_data;
+        self.cc_data = cc_data;
+        self.framerate = framerate;
         self.time_code = time_code;
         self.sequence = sequence_count;
+        self.flags = flags;
 
         Ok(())
     }
 
-    /// Clear any internal buffers
-    pub fn flush(&mut self) {
-        *self = Self::default();
+    fn check_cea608_field_order(cc_data: &[u8]) -> Result<(), ParserError> {
+        let mut have_field1 = false;
+        for triple in cc_data.chunks_exact(3) {
+            let cc_valid = (triple[0] & 0x04) == 0x04;
+            let cc_type = triple[0] & 0x3;
+            if (cc_type & 0b10) > 0 {
+                // the rest is CEA-708 data
+                break;
+            }
+            if !cc_valid {
+                continue;
+            }
+            if cc_type == 0b00 {
+                have_field1 = true;
+            } else if !have_field1 {
+                warn!(
+                    target: PARSER_LOG_TARGET,
+                    "CEA-608 field 2 data without preceding field 1 data"
+                );
+                return Err(ParserError::Cea608FieldOrder);
+            }
+        }
+        Ok(())
     }
 
-    pub fn time_code(&self) -> Option<TimeCode> {
-        self.time_code
+    /// Whether CEA-608 field ordering within a cc_data section is validated.  When enabled, valid
+    /// field 2 byte pairs must be preceded by a valid field 1 byte pair in the same CDP otherwise
+    /// [`ParserError::Cea608FieldOrder`] is returned.  A warning is also logged for any CEA-608
+    /// byte without odd parity.
+    ///
+    /// Disabled by default.
+    pub fn set_strict_cea608(&mut self, strict_cea608: bool) {
+        self.options.strict_cea608 = strict_cea608;
     }
 
-    pub fn framerate(&self) -> Option<Framerate> {
-        self.framerate
+    /// Whether CEA-608 field ordering is validated
+    pub fn strict_cea608(&self) -> bool {
+        self.options.strict_cea608
     }
 
-    pub fn sequence(&self) -> u16 {
-        self.sequence
+    /// Whether to attempt to recover from an unknown section identifier by skipping to the
+    /// footer.  When enabled, a warning is logged and parsing continues from the footer if the
+    /// last four bytes of the `CDP` start with the footer identifier.  The checksum is still
+    /// validated.
+    ///
+    /// Disabled by default, in which case [`ParserError::WrongMagic`] is returned.
+    pub fn set_recover_on_error(&mut self, recover_on_error: bool) {
+        self.options.recover_on_error = recover_on_error;
     }
 
-    /// Pop a valid [`cea708_types::DTVCCPacket`] or None if no packet could be parsed
-    pub fn pop_packet(&mut self) -> Option<cea708_types::DTVCCPacket> {
-        self.cc_data_parser.pop_packet()
+    /// Whether recovering from unknown section identifiers is enabled
+    pub fn recover_on_error(&self) -> bool {
+        self.options.recover_on_error
     }
 
-    pub fn cea608(&mut self) -> Option<&[cea708_types::Cea608]> {
-        self.cc_data_parser.cea608()
+    /// Whether to parse CDPs with an unknown framerate identifier, such as the unspecified
+    /// identifier 0x0 used by some equipment.  When enabled, [`CDPParser::framerate`] returns
+    /// `None` for such CDPs and the rest of the CDP is parsed as usual.
+    ///
+    /// SMPTE 334-2 does not allow these identifiers so this is disabled by default and
+    /// [`ParserError::UnknownFramerate`] is returned.
+    pub fn set_allow_unknown_framerate(&mut self, allow_unknown_framerate: bool) {
+        self.options.allow_unknown_framerate = allow_unknown_framerate;
     }
-}
 
-/// A struct for writing cc_data packets
-#[derive(Debug)]
-pub struct CDPWriter {
-    cc_data: cea708_types::CCDataWriter,
-    time_code: Option<TimeCode>,
-    frame_rate: Framerate,
-    sequence_count: u16,
-}
+    /// Whether CDPs with an unknown framerate identifier are parsed
+    pub fn allow_unknown_framerate(&self) -> bool {
+        self.options.allow_unknown_framerate
+    }
 
-impl CDPWriter {
-    pub fn new(frame_rate: Framerate) -> Self {
-        Self {
-            cc_data: cea708_types::CCDataWriter::default(),
-            time_code: None,
-            frame_rate,
-            sequence_count: 0,
-        }
+    /// Whether to reject a service information section that does not describe any services.
+    /// When enabled, [`ParserError::EmptyServiceInfo`] is returned for such CDPs.
+    ///
+    /// Disabled by default.
+    pub fn set_strict_svc_info(&mut self, strict_svc_info: bool) {
+        self.options.strict_svc_info = strict_svc_info;
     }
 
-    /// Push a [`cea708_types::DTVCCPacket`] for writing
-    pub fn push_packet(&mut self, packet: cea708_types::DTVCCPacket) {
-        self.cc_data.push_packet(packet)
+    /// Whether service information sections without any services are rejected
+    pub fn strict_svc_info(&self) -> bool {
+        self.options.strict_svc_info
     }
 
-    /// Push a [`cea708_types::Cea608`] byte pair for writing
-    pub fn push_cea608(&mut self, cea608: cea708_types::Cea608) {
-        self.cc_data.push_cea608(cea608)
+    /// Whether to ignore any bytes following the CDP, e.g. when CDPs are padded to a fixed size.
+    /// The length of the CDP is taken from the CDP header and the checksum is only calculated over
+    /// the CDP.
+    ///
+    /// Disabled by default, in which case [`ParserError::LengthMismatch`] is returned if `data`
+    /// is longer than the CDP.
+    pub fn set_allow_trailing(&mut self, allow_trailing: bool) {
+        self.options.allow_trailing = allow_trailing;
     }
 
-    pub fn set_time_code(&mut self, time_code: Option<TimeCode>) {
-        self.time_code = time_code;
+    /// Whether bytes following the CDP are ignored
+    pub fn allow_trailing(&self) -> bool {
+        self.options.allow_trailing
     }
 
-    /// Set the next packet's sequence count to a specific value
-    pub fn set_sequence_count(&mut self, sequence: u16) {
-        self.sequence_count = sequence;
+    /// Compatibility mode for CDPs with a length that is larger than the contained sections, e.g.
+    /// from encoders that reserve space in each CDP.  When enabled, the footer is expected at the
+    /// end of the length advertised in the CDP header and any bytes between the last time code,
+    /// cc_data or service information section and the footer are ignored, including future
+    /// sections.  The checksum is still calculated over the whole CDP.
+    ///
+    /// Disabled by default, in which case such CDPs fail to parse.
+    pub fn set_trust_cdp_len(&mut self, trust_cdp_len: bool) {
+        self.options.trust_cdp_len = trust_cdp_len;
     }
 
-    /// Clear all stored data
-    pub fn flush(&mut self) {
-        self.cc_data.flush();
-        self.time_code = None;
-        self.sequence_count = 0;
+    /// Whether the footer is expected at the end of the advertised CDP length
+    pub fn trust_cdp_len(&self) -> bool {
+        self.options.trust_cdp_len
     }
 
-    /// Write the next CDP packet taking the next relevant CEA-608 byte pairs and
-    /// [`cea708_types::DTVCCPacket`]s.
-    pub fn write<W: std::io::Write>(&mut self, w: &mut W) -> Result<(), std::io::Error> {
-        let mut len = 7; // header
-        if self.time_code.is_some() {
-            len += 5;
-        }
-        let mut cc_data = Vec::new();
-        self.cc_data.write(
-            cea708_types::Framerate::new(self.frame_rate.numer(), self.frame_rate.denom()),
-            &mut cc_data,
-        )?;
-        cc_data[1] = 0xe0 | (cc_data[0] & 0x1f);
-        cc_data[0] = 0x72;
-        len += cc_data.len();
-        len += 4; // footer
+    /// Whether to parse a cc_data section with a cc_count that is larger than the data contained
+    /// in the CDP.  When enabled, a warning is logged and the cc_count is reduced to the number of
+    /// complete cc_data triples before the footer.  A CDP with a service information section
+    /// after a truncated cc_data section still fails to parse.
+    ///
+    /// Disabled by default, in which case such CDPs fail to parse.
+    pub fn set_recover_truncated_ccdata(&mut self, recover_truncated_ccdata: bool) {
+        self.options.recover_truncated_ccdata = recover_truncated_ccdata;
+    }
 
-        assert!(len <= u8::MAX as usize);
+    /// Whether cc_data sections with too large a cc_count are truncated
+    pub fn recover_truncated_ccdata(&self) -> bool {
+        self.options.recover_truncated_ccdata
+    }
 
-        let mut flags = Flags::CC_DATA_PRESENT | 0x1;
-        if self.time_code.is_some() {
-            flags |= Flags::TIME_CODE_PRESENT;
-        }
+    /// Parse all of the complete `CDP` packets contained back to back in `data` returning the
+    /// number of bytes consumed.
+    ///
+    /// A trailing incomplete `CDP` is not consumed and should be provided again with the
+    /// remaining data.  [`ParserError::WrongMagic`] is returned for remaining data that does not
+    /// start with the `CDP` magic, even if it is too short to be a complete `CDP`.  The packets
+    /// and CEA-608 byte pairs of all the parsed `CDP`s are accumulated while the time code,
+    /// framerate, sequence count and flags are those of the last parsed `CDP`.  If any `CDP` fails
+    /// to parse, the error is returned and the `CDP`s before it have been processed.
+    pub fn parse_all(&mut self, data: &[u8]) -> Result<usize, ParserError> {
+        let mut cea608 = vec![];
+        let mut has_caption_data = false;
+        let mut consumed = 0;
+        let ret = loop {
+            let remaining = &data[consumed..];
+            if !CDP_MAGIC.starts_with(&remaining[..remaining.len().min(CDP_MAGIC.len())]) {
+                break Err(ParserError::WrongMagic);
+            }
+            if remaining.len() < 3 || remaining.len() < remaining[2] as usize {
+                break Ok(consumed);
+            }
+            let len = remaining[2] as usize;
+            if let Err(e) = self.parse(&remaining[..len]) {
+                break Err(e);
+            }
+            cea608.extend_from_slice(&self.cea608);
+            has_caption_data |= self.has_caption_data;
+            consumed += len;
+        };
+        self.cea608 = cea608;
+        self.has_caption_data = has_caption_data;
+        ret
+    }
 
-        let mut checksum: u8 = 0;
-        let data = [
-            0x96,
-            0x69,
-            (len & 0xff) as u8,
-            self.frame_rate.id << 4 | 0x0f,
-            flags,
-            ((self.sequence_count & 0xff00) >> 8) as u8,
-            (self.sequence_count & 0xff) as u8,
-        ];
-        for v in data.iter() {
-            checksum = checksum.wrapping_add(*v);
+    /// Read a single complete `CDP` packet from `r` and parse it.
+    ///
+    /// Only the bytes of a single `CDP` are read from `r`.  Failures reading from `r` are
+    /// returned as [`ParserError::Io`].
+    pub fn parse_from_reader<R: std::io::Read>(&mut self, r: &mut R) -> Result<(), ParserError> {
+        let mut data = vec![0; 3];
+        r.read_exact(&mut data)?;
+        if data[..2] != CDP_MAGIC {
+            return Err(ParserError::WrongMagic);
         }
-        w.write_all(&data)?;
 
-        if let Some(time_code) = self.time_code {
-            let data = [
-                0x71,
-                0xc0 | ((time_code.hours / 10) << 4) | (time_code.hours % 10),
-                0x80 | ((time_code.minutes / 10) << 4) | (time_code.minutes % 10),
-                ((time_code.field & 0x1) << 7)
-                    | ((time_code.seconds / 10) << 4)
-                    | (time_code.seconds % 10),
-                if time_code.drop_frame { 0x80 } else { 0x0 }
-                    | ((time_code.frames / 10) << 4)
-                    | (time_code.frames % 10),
-            ];
-            for v in data.iter() {
-                checksum = checksum.wrapping_add(*v);
-            }
-            w.write_all(&data)?;
+        let len = data[2] as usize;
+        if len > data.len() {
+            data.resize(len, 0);
+            r.read_exact(&mut data[3..])?;
         }
 
-        for v in cc_data.iter() {
-            checksum = checksum.wrapping_add(*v);
-        }
-        w.write_all(&cc_data)?;
+        self.parse(&data)
+    }
 
-        let data = [
-            0x74,
+    /// Clear any internal buffers
+    pub fn flush(&mut self) {
+        *self = Self {
+            options: self.options,
+            stats: self.stats,
+            ..Self::default()
+        };
+    }
+
+    /// Clear the pending [`cea708_types::DTVCCPacket`]s, [`cea708_types::Cea608`] byte pairs and
+    /// any partially parsed caption data while keeping the framerate, time code, sequence count
+    /// and flags of the last parsed CDP.
+    pub fn clear_captions(&mut self) {
+        self.cc_data_parser.flush();
+        self.cc_data_parser.handle_cea608();
+        self.packets.clear();
+        self.cea608.clear();
+        self.cc_data = None;
+        self.has_caption_data = false;
+        self.cc_data_padding_only = false;
+    }
+
+    pub fn time_code(&self) -> Option<TimeCode> {
+        self.time_code
+    }
+
+    pub fn framerate(&self) -> Option<Framerate> {
+        self.framerate
+    }
+
+    /// The framerate identifier of the last parsed CDP as defined by SMPTE 334-2:
+    ///
+    /// | id  | framerate  |
+    /// |-----|------------|
+    /// | 0x1 | 24000/1001 |
+    /// | 0x2 | 24         |
+    /// | 0x3 | 25         |
+    /// | 0x4 | 30000/1001 |
+    /// | 0x5 | 30         |
+    /// | 0x6 | 50         |
+    /// | 0x7 | 60000/1001 |
+    /// | 0x8 | 60         |
+    pub fn framerate_id(&self) -> Option<u8> {
+        self.framerate.map(|framerate| framerate.id())
+    }
+
+    pub fn sequence(&self) -> u16 {
+        self.sequence
+    }
+
+    /// The value of the reserved bit in the flags of the last parsed CDP.  SMPTE 334-2 requires
+    /// this to be set.
+    pub fn reserved_flag(&self) -> bool {
+        self.flags.reserved()
+    }
+
+    /// A copy of the CDP level values of the last parsed CDP.  Pending packets and CEA-608 byte
+    /// pairs are not included.
+    pub fn snapshot(&self) -> CDPParserSnapshot {
+        CDPParserSnapshot {
+            framerate: self.framerate,
+            sequence: self.sequence,
+            time_code: self.time_code,
+            flags: self.flags,
+        }
+    }
+
+    /// The [`Flags`] of the last parsed CDP describing which sections were present
+    pub fn sections_present(&self) -> Flags {
+        self.flags
+    }
+
+    /// Statistics about the CDPs passed to [`CDPParser::parse`] since the parser was created or
+    /// [`CDPParser::reset_stats`] was called.  The statistics are not reset by
+    /// [`CDPParser::flush`].
+    pub fn stats(&self) -> &ParserStats {
+        &self.stats
+    }
+
+    /// Reset the statistics returned by [`CDPParser::stats`]
+    pub fn reset_stats(&mut self) {
+        self.stats = ParserStats::default();
+    }
+
+    /// The flags byte of the last parsed CDP exactly as it was contained in the CDP
+    pub fn flags_byte(&self) -> u8 {
+        self.flags.into()
+    }
+
+    /// Whether the caption service active flag was set in the last parsed CDP.  The cc_data of a
+    /// CDP is parsed regardless of this flag.
+    pub fn caption_service_active(&self) -> bool {
+        self.flags.caption_service_active()
+    }
+
+    /// Whether the svc_info start flag was set in the last parsed CDP, or `None` if the last
+    /// parsed CDP did not contain a svc_info section
+    pub fn service_info_start(&self) -> Option<bool> {
+        self.flags.svc_info().then_some(self.flags.svc_info_start())
+    }
+
+    /// Whether the svc_info change flag was set in the last parsed CDP, or `None` if the last
+    /// parsed CDP did not contain a svc_info section
+    pub fn service_info_change(&self) -> Option<bool> {
+        self.flags
+            .svc_info()
+            .then_some(self.flags.svc_info_change())
+    }
+
+    /// Whether the svc_info complete flag was set in the last parsed CDP, or `None` if the last
+    /// parsed CDP did not contain a svc_info section
+    pub fn service_info_complete(&self) -> Option<bool> {
+        self.flags
+            .svc_info()
+            .then_some(self.flags.svc_info_complete())
+    }
+
+    /// Pop a valid [`cea708_types::DTVCCPacket`] or None if no packet could be parsed
+    pub fn pop_packet(&mut self) -> Option<cea708_types::DTVCCPacket> {
+        self.packets.pop_front()
+    }
+
+    /// Whether the last parsed CDP contained any caption data.  A CDP with a cc_data section
+    /// containing only padding, or a CDP without a cc_data section, has no caption data.
+    ///
+    /// [`cea708_types::DTVCCPacket`]s without any services and CEA-608 byte pairs of `0x80 0x80`
+    /// are considered padding.
+    pub fn has_caption_data(&self) -> bool {
+        self.has_caption_data
+    }
+
+    /// Whether the cc_data section flag was set in the last parsed CDP, regardless of whether the
+    /// cc_data section contained any caption data.  Together with
+    /// [`CDPParser::has_caption_data`] this distinguishes a CDP without a cc_data section from a
+    /// CDP with a cc_data section containing only padding.
+    pub fn had_cc_data_section(&self) -> bool {
+        self.flags.cc_data()
+    }
+
+    /// Whether the last parsed CDP contained a cc_data section with only padding.  Returns false
+    /// if the CDP did not contain a cc_data section.  See [`CDPParser::has_caption_data`] for
+    /// what is considered padding.
+    pub fn cc_data_was_padding_only(&self) -> bool {
+        self.cc_data_padding_only
+    }
+
+    /// The number of [`cea708_types::DTVCCPacket`]s that can be popped
+    pub fn packet_count(&self) -> usize {
+        self.packets.len()
+    }
+
+    /// The number of [`cea708_types::Cea608`] byte pairs returned by [`CDPParser::cea608`]
+    pub fn cea608_count(&self) -> usize {
+        self.cea608.len()
+    }
+
+    /// Iterate over and remove all the valid [`cea708_types::DTVCCPacket`]s in the order they
+    /// were parsed
+    pub fn drain_packets(&mut self) -> impl Iterator<Item = cea708_types::DTVCCPacket> + '_ {
+        std::iter::from_fn(move || self.pop_packet())
+    }
+
+    /// The [`cea708_types::Cea608`] byte pairs of the last parsed CDP, or of all the CDPs parsed
+    /// by the last call to [`CDPParser::parse_all`].  `None` if there were no CEA-608 byte pairs.
+    pub fn cea608(&self) -> Option<&[cea708_types::Cea608]> {
+        if self.cea608.is_empty() {
+            None
+        } else {
+            Some(&self.cea608)
+        }
+    }
+
+    /// An owned copy of the [`cea708_types::Cea608`] byte pairs returned by
+    /// [`CDPParser::cea608`] that does not borrow the parser, e.g. for interleaving with
+    /// [`CDPParser::pop_packet`].
+    pub fn cea608_cloned(&self) -> Vec<cea708_types::Cea608> {
+        self.cea608.clone()
+    }
+
+    /// Take the cc_data of the last parsed CDP in the format accepted by
+    /// [`cea708_types::CCDataParser::push`] or None if the CDP did not contain a cc_data section.
+    ///
+    /// The returned data consists of the two byte cc_data header followed by the cc_data triples
+    /// as contained in the CDP.
+    pub fn take_cc_data(&mut self) -> Option<Vec<u8>> {
+        self.cc_data.take()
+    }
+
+    /// The [`cea708_types::CCDataParser`] used for parsing the cc_data section of each CDP.
+    ///
+    /// Parsing the CDP header, time code and footer remains the responsibility of this parser.
+    pub fn cc_data_parser(&self) -> &cea708_types::CCDataParser {
+        &self.cc_data_parser
+    }
+}
+
+/// The CEA-608 fields for which padding is written by a [`CDPWriter`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Cea608Fields {
+    /// Only write padding for CEA-608 field 1
+    Field1Only,
+    /// Only write padding for CEA-608 field 2
+    Field2Only,
+    /// Write padding for both CEA-608 fields
+    #[default]
+    Both,
+}
+
+/// A struct for writing cc_data packets
+#[derive(Debug)]
+pub struct CDPWriter {
+    cc_data: cea708_types::CCDataWriter,
+    packets: std::collections::VecDeque<cea708_types::DTVCCPacket>,
+    time_code: Option<TimeCode>,
+    frame_rate: Framerate,
+    sequence_count: u16,
+    auto_sequence: bool,
+    caption_service_active: bool,
+    reserved_flag: bool,
+    unknown_framerate: bool,
+    max_len: u8,
+    section_order: [CdpSection; 2],
+    raw_cc_data: Option<Vec<u8>>,
+    pending_cc_data: Option<Vec<u8>>,
+    last_padding_count: usize,
+    cea608_fields: Cea608Fields,
+    ccdata_marker_bits: u8,
+    last_cea608_was_field1: bool,
+    cea708_pending_count: usize,
+}
+
+impl CDPWriter {
+    pub fn new(frame_rate: Framerate) -> Self {
+        Self {
+            cc_data: cea708_types::CCDataWriter::default(),
+            packets: std::collections::VecDeque::new(),
+            time_code: None,
+            frame_rate,
+            sequence_count: 0,
+            auto_sequence: false,
+            caption_service_active: false,
+            reserved_flag: true,
+            unknown_framerate: false,
+            max_len: u8::MAX,
+            section_order: [CdpSection::TimeCode, CdpSection::CcData],
+            raw_cc_data: None,
+            pending_cc_data: None,
+            last_padding_count: 0,
+            cea608_fields: Cea608Fields::Both,
+            ccdata_marker_bits: 0xe0,
+            last_cea608_was_field1: false,
+            cea708_pending_count: 0,
+        }
+    }
+
+    /// Create a [`CDPWriterBuilder`] for configuring a new [`CDPWriter`]
+    pub fn builder(frame_rate: Framerate) -> CDPWriterBuilder {
+        CDPWriterBuilder::new(frame_rate)
+    }
+
+    /// Whether to output padding when not enough CEA-608 or CEA-708 data has been provided to
+    /// fill a CDP.  This also sets [`CDPWriter::set_output_cea608_padding`].
+    pub fn set_output_padding(&mut self, output_padding: bool) {
+        self.cc_data.set_output_padding(output_padding);
+        self.cc_data.set_output_cea608_padding(output_padding);
+    }
+
+    /// Whether padding will be written
+    pub fn output_padding(&self) -> bool {
+        self.cc_data.output_padding()
+    }
+
+    /// Whether to output CEA-608 padding byte pairs when not enough CEA-608 data has been
+    /// provided.  CEA-708 padding is not affected.
+    pub fn set_output_cea608_padding(&mut self, output_cea608_padding: bool) {
+        self.cc_data
+            .set_output_cea608_padding(output_cea608_padding);
+    }
+
+    /// Whether CEA-608 padding will be written
+    pub fn output_cea608_padding(&self) -> bool {
+        self.cc_data.output_cea608_padding()
+    }
+
+    /// The CEA-608 fields for which padding byte pairs are written when CEA-608 padding is
+    /// enabled and not enough CEA-608 data has been provided.  CEA-608 padding for the other field
+    /// is omitted or replaced by CEA-708 padding if [`CDPWriter::output_padding`] is enabled.
+    /// Pushed CEA-608 data is written regardless of this setting.
+    ///
+    /// Defaults to [`Cea608Fields::Both`].
+    pub fn set_cea608_fields(&mut self, cea608_fields: Cea608Fields) {
+        self.cea608_fields = cea608_fields;
+    }
+
+    /// The CEA-608 fields for which padding is written
+    pub fn cea608_fields(&self) -> Cea608Fields {
+        self.cea608_fields
+    }
+
+    /// Whether to increment the sequence count after every written CDP
+    pub fn set_auto_sequence(&mut self, auto_sequence: bool) {
+        self.auto_sequence = auto_sequence;
+    }
+
+    /// Whether the sequence count is incremented after every written CDP
+    pub fn auto_sequence(&self) -> bool {
+        self.auto_sequence
+    }
+
+    /// Set the caption service active flag in written CDPs
+    pub fn set_caption_service_active(&mut self, caption_service_active: bool) {
+        self.caption_service_active = caption_service_active;
+    }
+
+    /// Whether the caption service active flag is set in written CDPs
+    pub fn caption_service_active(&self) -> bool {
+        self.caption_service_active
+    }
+
+    /// Take the state of the last CDP parsed by `parser` for writing the next CDP.
+    ///
+    /// The time code, sequence count, caption service active and reserved flags are copied from
+    /// `parser` and all of the pending [`cea708_types::DTVCCPacket`]s and
+    /// [`cea708_types::Cea608`] byte pairs are moved into this writer.  Service information is
+    /// not parsed by [`CDPParser`] and is therefore not transferred.
+    pub fn take_from_parser(&mut self, parser: &mut CDPParser) {
+        self.set_time_code(parser.time_code());
+        self.set_sequence_count(parser.sequence());
+        self.set_caption_service_active(parser.caption_service_active());
+        self.set_reserved_flag(parser.reserved_flag());
+        for packet in parser.drain_packets() {
+            self.push_packet(packet);
+        }
+        for cea608 in std::mem::take(&mut parser.cea608) {
+            self.push_cea608(cea608);
+        }
+    }
+
+    /// Push a [`cea708_types::DTVCCPacket`] for writing.
+    ///
+    /// Packets are written in the order they are pushed.  Each written CDP contains as many whole
+    /// packets as fit into the cc_data triples available at the framerate and packets are never
+    /// split across CDPs.  The only exception is a packet that is too large for a single CDP which
+    /// is written on its own, spread over as many CDPs as needed.
+    pub fn push_packet(&mut self, packet: cea708_types::DTVCCPacket) {
+        self.packets.push_back(packet)
+    }
+
+    /// The next [`cea708_types::DTVCCPacket`] that will be written.
+    ///
+    /// Only packets that have not been taken for a CDP yet are considered.  A packet that is
+    /// partially written because it is too large for a single CDP is not returned.
+    pub fn peek_next_packet(&self) -> Option<&cea708_types::DTVCCPacket> {
+        self.packets.front()
+    }
+
+    /// The number of [`cea708_types::DTVCCPacket`]s that have not been taken for a CDP yet.  See
+    /// [`CDPWriter::peek_next_packet`].
+    pub fn queued_packet_count(&self) -> usize {
+        self.packets.len()
+    }
+
+    /// Push a [`cea708_types::Cea608`] byte pair for writing
+    pub fn push_cea608(&mut self, cea608: cea708_types::Cea608) {
+        self.cc_data.push_cea608(cea608)
+    }
+
+    /// The [`cea708_types::CCDataWriter`] used for writing the cc_data section of each CDP for
+    /// configuring options that are not exposed by this writer.
+    ///
+    /// Writing the CDP header, time code and footer remains the responsibility of this writer.
+    pub fn cc_data_writer_mut(&mut self) -> &mut cea708_types::CCDataWriter {
+        &mut self.cc_data
+    }
+
+    /// Set the cc_data of the next CDP from an externally produced cc_data block consisting of the
+    /// two byte cc_data header followed by the cc_data triples, e.g. as returned by
+    /// [`CDPParser::take_cc_data`].
+    ///
+    /// The cc_data triples are written as-is in place of the data queued with
+    /// [`CDPWriter::push_packet`] and [`CDPWriter::push_cea608`] which is kept for the following
+    /// CDPs.  [`WriterError::InvalidCcData`] is returned if the length of `cc_data` does not match
+    /// the cc_count in the header or if the cc_count is too large for the framerate.
+    pub fn set_cc_data(&mut self, cc_data: &[u8]) -> Result<(), WriterError> {
+        if cc_data.len() < 2 {
+            return Err(WriterError::InvalidCcData);
+        }
+        let cc_count = cc_data[0] & 0x1f;
+        if cc_data.len() != 2 + cc_count as usize * 3 || cc_count > self.frame_rate.max_cc_count() {
+            return Err(WriterError::InvalidCcData);
+        }
+        let mut section = vec![CC_DATA_ID, 0xe0 | cc_count];
+        section.extend_from_slice(&cc_data[2..]);
+        self.raw_cc_data = Some(section);
+        Ok(())
+    }
+
+    /// Push a CEA-608 field 1 and field 2 byte pair for writing.  The top bit of every byte is
+    /// set as required for odd parity.
+    pub fn push_cea608_pair(&mut self, field1: (u8, u8), field2: (u8, u8)) {
+        self.push_cea608(cea708_types::Cea608::Field1(
+            set_odd_parity(field1.0),
+            set_odd_parity(field1.1),
+        ));
+        self.push_cea608(cea708_types::Cea608::Field2(
+            set_odd_parity(field2.0),
+            set_odd_parity(field2.1),
+        ));
+    }
+
+    pub fn set_time_code(&mut self, time_code: Option<TimeCode>) {
+        self.time_code = time_code;
+    }
+
+    /// Stop writing a time code section in the following CDPs.  Equivalent to
+    /// `set_time_code(None)`.
+    pub fn clear_time_code(&mut self) {
+        self.set_time_code(None);
+    }
+
+    /// Set the next packet's sequence count to a specific value
+    pub fn set_sequence_count(&mut self, sequence: u16) {
+        self.sequence_count = sequence;
+    }
+
+    /// Set the value of the reserved bit in the flags of written CDPs.
+    ///
+    /// SMPTE 334-2 requires this bit to be set which is the default.  This is only useful for
+    /// reproducing CDPs produced by non-conforming equipment.
+    pub fn set_reserved_flag(&mut self, reserved: bool) {
+        self.reserved_flag = reserved;
+    }
+
+    /// The value of the reserved bit in the flags of written CDPs
+    pub fn reserved_flag(&self) -> bool {
+        self.reserved_flag
+    }
+
+    /// Override the marker bits (the 3 most significant bits) of the cc_count byte in the cc_data
+    /// section of written CDPs.  The 5 least significant bits of `marker_bits` are ignored.
+    ///
+    /// SMPTE 334-2 requires all marker bits to be set (`0xe0`) which is the default.  Written CDPs
+    /// with any other value are not conforming.  This is only available with the
+    /// `conformance-testing` feature for testing the error handling of CDP decoders.
+    #[cfg(feature = "conformance-testing")]
+    pub fn set_ccdata_marker_bits(&mut self, marker_bits: u8) {
+        self.ccdata_marker_bits = marker_bits & 0xe0;
+    }
+
+    /// Write the unspecified framerate identifier 0x0 instead of the identifier of the writer's
+    /// framerate.  The writer's framerate is still used for limiting the amount of cc_data in each
+    /// CDP.
+    ///
+    /// SMPTE 334-2 does not allow this identifier.  This is only useful for reproducing CDPs
+    /// produced by non-conforming equipment.
+    pub fn set_unknown_framerate(&mut self, unknown_framerate: bool) {
+        self.unknown_framerate = unknown_framerate;
+    }
+
+    /// Whether the unspecified framerate identifier is written
+    pub fn unknown_framerate(&self) -> bool {
+        self.unknown_framerate
+    }
+
+    /// Set the maximum length of a written CDP.  Defaults to 255, the largest length that can be
+    /// signalled in a CDP.
+    ///
+    /// Writing a CDP longer than `max_len` fails with [`WriterError::PacketTooLarge`] and no data
+    /// is consumed.
+    pub fn set_max_len(&mut self, max_len: u8) {
+        self.max_len = max_len;
+    }
+
+    /// The maximum length of a written CDP
+    pub fn max_len(&self) -> u8 {
+        self.max_len
+    }
+
+    /// Set the order in which the time code and cc_data sections are written.  This is intended
+    /// for testing decoders as SMPTE 334-2 requires the time code section to precede the cc_data
+    /// section which is the default.  The length and checksum of the CDP are still correct.
+    ///
+    /// `order` must contain [`CdpSection::TimeCode`] and [`CdpSection::CcData`] exactly once
+    /// otherwise [`WriterError::InvalidSectionOrder`] is returned and the order is not changed.
+    /// Sections without any data are still omitted.
+    pub fn set_section_order(&mut self, order: &[CdpSection]) -> Result<(), WriterError> {
+        let [first, second] = order else {
+            return Err(WriterError::InvalidSectionOrder);
+        };
+        match (first, second) {
+            (CdpSection::TimeCode, CdpSection::CcData)
+            | (CdpSection::CcData, CdpSection::TimeCode) => {
+                self.section_order = [*first, *second];
+                Ok(())
+            }
+            _ => Err(WriterError::InvalidSectionOrder),
+        }
+    }
+
+    /// The order in which the time code and cc_data sections are written
+    pub fn section_order(&self) -> &[CdpSection] {
+        &self.section_order
+    }
+
+    /// Clear all stored data
+    pub fn flush(&mut self) {
+        self.cc_data.flush();
+        self.packets.clear();
+        self.cea708_pending_count = 0;
+        self.time_code = None;
+        self.sequence_count = 0;
+        self.raw_cc_data = None;
+        self.pending_cc_data = None;
+        self.last_padding_count = 0;
+    }
+
+    /// Write the next CDP packet taking the next relevant CEA-608 byte pairs and
+    /// [`cea708_types::DTVCCPacket`]s.
+    ///
+    /// The number of cc_data triples in a CDP is limited by the framerate to keep within the
+    /// CEA-708 bitrate (see [`Framerate::max_cc_count`]).  Any data that does not fit into this
+    /// CDP is kept for the following CDPs.
+    ///
+    /// Writing a CDP that was previously parsed with [`CDPParser`] is not necessarily byte for
+    /// byte identical to the original.  The following are not preserved:
+    ///
+    /// - service information and future sections are never written,
+    /// - the cc_count of the cc_data section is determined by the queued data and padding
+    ///   configuration, not the original cc_count, and the cc_data section is omitted when the
+    ///   cc_count would be 0,
+    /// - padding and invalid cc_data triples in the original are not reproduced,
+    /// - the reserved bits in the frame rate byte are always set,
+    /// - the reserved bit in the flags byte is set unless changed with
+    ///   [`CDPWriter::set_reserved_flag`],
+    /// - the caption service active flag is only set as configured with
+    ///   [`CDPWriter::set_caption_service_active`].
+    pub fn write<W: std::io::Write>(&mut self, w: &mut W) -> Result<(), WriterError> {
+        let mut data = [0; u8::MAX as usize];
+        let len = self.write_into(&mut data)?;
+        w.write_all(&data[..len])?;
+        Ok(())
+    }
+
+    /// Write the next CDP packet into `buf` returning the number of bytes written.
+    ///
+    /// If `buf` is too small to contain the CDP, [`WriterError::WouldOverflow`] is returned and no
+    /// data is consumed.  See [`CDPWriter::set_max_len`] for limiting the length of the CDP.  See
+    /// [`CDPWriter::write`] for more details.
+    pub fn write_into(&mut self, buf: &mut [u8]) -> Result<usize, WriterError> {
+        self.cc_data_section();
+        let len = self.packet_len();
+        assert!(len <= u8::MAX as usize);
+        if len > self.max_len as usize {
+            return Err(WriterError::PacketTooLarge {
+                len,
+                max_len: self.max_len,
+            });
+        }
+        if buf.len() < len {
+            return Err(WriterError::WouldOverflow(len - buf.len()));
+        }
+        let flags = self.flags();
+        let cc_data = self.pending_cc_data.take().unwrap();
+        let has_cc_data = flags.cc_data();
+        self.last_padding_count = cc_data[CC_DATA_HEADER_LEN..]
+            .chunks_exact(3)
+            .filter(|triple| {
+                let cc_valid = (triple[0] & 0x04) == 0x04;
+                let is_cea608 = (triple[0] & 0x02) == 0;
+                !cc_valid || (is_cea608 && triple[1] == 0x80 && triple[2] == 0x80)
+            })
+            .count();
+
+        let mut idx = 0;
+        let mut push = |data: &[u8]| {
+            buf[idx..idx + data.len()].copy_from_slice(data);
+            idx += data.len();
+        };
+
+        push(&[
+            CDP_MAGIC[0],
+            CDP_MAGIC[1],
+            (len & 0xff) as u8,
+            if self.unknown_framerate {
+                0x0f
+            } else {
+                self.frame_rate.id << 4 | 0x0f
+            },
+            flags.into(),
             ((self.sequence_count & 0xff00) >> 8) as u8,
             (self.sequence_count & 0xff) as u8,
-        ];
-        for v in data.iter() {
-            checksum = checksum.wrapping_add(*v);
+        ]);
+
+        for section in self.section_order {
+            match section {
+                CdpSection::TimeCode => {
+                    if let Some(time_code) = self.time_code {
+                        push(&time_code.to_wire());
+                    }
+                }
+                CdpSection::CcData => {
+                    if has_cc_data {
+                        push(&cc_data);
+                    }
+                }
+                _ => unreachable!(),
+            }
         }
-        w.write_all(&data)?;
-        // 256 - checksum without having to use a type larger than u8
-        let checksum_byte = (!checksum).wrapping_add(1);
-        debug_assert!(checksum_byte == ((256 - checksum as u16) as u8));
-        w.write_all(&[checksum_byte])?;
 
-        Ok(())
+        push(&[
+            CDP_FOOTER_ID,
+            ((self.sequence_count & 0xff00) >> 8) as u8,
+            (self.sequence_count & 0xff) as u8,
+        ]);
+        debug_assert!(idx == len - 1);
+        buf[len - 1] = cdp_checksum(&buf[..len - 1]);
+
+        if self.auto_sequence {
+            self.sequence_count = self.sequence_count.wrapping_add(1);
+        }
+
+        Ok(len)
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
-    use crate::tests::*;
-    use cea708_types::{tables, Cea608, DTVCCPacket, Service};
+    /// The number of bytes the next call to [`CDPWriter::write`] or [`CDPWriter::write_into`]
+    /// will produce with the current configuration and queued data.
+    ///
+    /// No queued data is taken.  Data pushed after calling this function is still written in the
+    /// next CDP if it fits, changing the number of bytes produced.
+    pub fn serialized_len(&self) -> usize {
+        self.packet_len()
+    }
 
-    #[derive(Debug)]
-    struct ServiceData<'a> {
-        service_no: u8,
-        codes: &'a [tables::Code],
+    /// The number of padding cc_data triples in the last written CDP.  Padding triples are those
+    /// without the cc_valid bit set, such as the `0xfa 0x00 0x00` CEA-708 padding, and CEA-608
+    /// triples containing `0x80 0x80`.
+    pub fn last_padding_count(&self) -> usize {
+        self.last_padding_count
     }
 
-    #[derive(Debug)]
-    struct CCPacketData<'a> {
-        sequence_no: u8,
-        services: &'a [ServiceData<'a>],
+    /// The flags byte the next call to [`CDPWriter::write`] or [`CDPWriter::write_into`] will
+    /// produce with the current configuration and queued data.
+    ///
+    /// Like [`CDPWriter::serialized_len`], no queued data is taken.
+    pub fn flags_byte(&self) -> u8 {
+        self.flags().into()
     }
 
-    #[derive(Debug)]
-    struct CDPPacketData<'a> {
-        data: &'a [u8],
-        sequence_count: u16,
-        time_code: Option<TimeCode>,
-        packets: &'a [CCPacketData<'a>],
-        cea608: &'a [Cea608],
+    fn flags(&self) -> Flags {
+        let mut flags = Flags::default();
+        flags.set_cc_data(self.next_cc_count() > 0);
+        flags.set_time_code(self.time_code.is_some());
+        flags.set_caption_service_active(self.caption_service_active);
+        flags.set_reserved(self.reserved_flag);
+        flags
     }
 
-    #[derive(Debug)]
-    struct TestCCData<'a> {
-        framerate: Framerate,
-        cdp_data: &'a [CDPPacketData<'a>],
+    /// The number of CEA-608 byte pairs queued in the inner [`cea708_types::CCDataWriter`] for
+    /// field 1 and field 2.
+    fn queued_cea608_pairs(&self) -> (usize, usize) {
+        // the buffered duration is pairs * 1001 / 60000 seconds rounded up to the next
+        // microsecond which is always less than the duration of another pair
+        let pairs = |duration: std::time::Duration| {
+            (duration.as_micros() * 60_000 / 1_001_000_000) as usize
+        };
+        (
+            pairs(self.cc_data.buffered_cea608_field1_duration()),
+            pairs(self.cc_data.buffered_cea608_field2_duration()),
+        )
     }
 
-    static PARSE_CDP: [TestCCData; 4] = [
-        // simple packet with cc_data and a time code
-        TestCCData {
-            framerate: FRAMERATES[2],
-            cdp_data: &[CDPPacketData {
-                data: &[
-                    0x96, // magic
-                    0x69,
-                    0x18,               // cdp_len
-                    0x3f,               // framerate
-                    0x80 | 0x40 | 0x01, // flags
-                    0x12,               // sequence counter
-                    0x34,
-                    0x71,        // time code id
-                    0xc0 | 0x17, // hours
-                    0x80 | 0x59, // minutes
-                    0x80 | 0x57, // seconds
-                    0x80 | 0x18, // frames
-                    0x72,        // cc_data id
-                    0xe0 | 0x02, // cc_count
-                    0xFF,
-                    0x02,
-                    0x21,
-                    0xFE,
+    /// The number of CEA-608 byte pairs the inner [`cea708_types::CCDataWriter`] will attempt to
+    /// write in the next CDP, alternating between field 1 and field 2.
+    fn cea608_pair_count(&self) -> usize {
+        let pairs_per_frame = self.frame_rate.cea608_pairs_per_frame();
+        if self.cc_data.output_cea608_padding() {
+            return pairs_per_frame;
+        }
+        let (field1, field2) = self.queued_cea608_pairs();
+        pairs_per_frame.min(field1.max(field2 * 2))
+    }
+
+    /// The number of whole packets and their cc_data triples that fit into the next CDP.  No
+    /// packets are taken while the remainder of a packet that was too large for a single CDP is
+    /// still being written.
+    fn whole_packets_for_next_cdp(&self) -> (usize, usize) {
+        if self.cea708_pending_count > 0 {
+            return (0, 0);
+        }
+        let (field1, field2) = self.queued_cea608_pairs();
+        let has_cea608 = self.cc_data.output_cea608_padding() || field1 > 0 || field2 > 0;
+        let cea608_count = if has_cea608 {
+            self.frame_rate.cea608_pairs_per_frame()
+        } else {
+            0
+        };
+        let mut available = (self.frame_rate.max_cc_count() as usize).saturating_sub(cea608_count);
+        let mut n_packets = 0;
+        let mut total_cc_count = 0;
+        for packet in self.packets.iter() {
+            let cc_count = packet.len().div_ceil(2);
+            if cc_count > available && n_packets > 0 {
+                break;
+            }
+            available = available.saturating_sub(cc_count);
+            n_packets += 1;
+            total_cc_count += cc_count;
+        }
+        (n_packets, total_cc_count)
+    }
+
+    /// The number of cc_data triples in the next CDP, mirroring what
+    /// [`CDPWriter::cc_data_section`] would produce without taking any queued data.
+    fn next_cc_count(&self) -> usize {
+        if let Some(cc_data) = self.pending_cc_data.as_ref().or(self.raw_cc_data.as_ref()) {
+            return (cc_data.len() - CC_DATA_HEADER_LEN) / 3;
+        }
+        let max_cc_count = self.frame_rate.max_cc_count() as usize;
+        let cea608_pairs = self.cea608_pair_count();
+        let cea708_count = self.cea708_pending_count + self.whole_packets_for_next_cdp().1;
+        let header_count = if self.output_padding() {
+            max_cc_count
+        } else {
+            max_cc_count.min(cea608_pairs + cea708_count)
+        };
+
+        let (mut field1, mut field2) = self.queued_cea608_pairs();
+        let cea608_padding = self.cc_data.output_cea608_padding();
+        let mut was_field1 = self.last_cea608_was_field1;
+        let mut written = 0;
+        let mut skipped_padding = 0;
+        for _ in 0..cea608_pairs {
+            if !was_field1 {
+                if field1 > 0 {
+                    field1 -= 1;
+                    written += 1;
+                } else if field2 > 0 {
+                    written += 1;
+                } else if cea608_padding {
+                    written += 1;
+                    if self.cea608_fields == Cea608Fields::Field2Only {
+                        skipped_padding += 1;
+                    }
+                }
+            } else if field2 > 0 {
+                field2 -= 1;
+                written += 1;
+            } else if cea608_padding {
+                written += 1;
+                if self.cea608_fields == Cea608Fields::Field1Only {
+                    skipped_padding += 1;
+                }
+            }
+            was_field1 = !was_field1;
+        }
+
+        let remaining = header_count - written;
+        let cea708_written = cea708_count.min(remaining);
+        written += cea708_written;
+        if cea708_written == cea708_count && self.output_padding() {
+            written += remaining - cea708_written;
+        }
+
+        match self.cea608_fields {
+            Cea608Fields::Both => written,
+            _ if self.output_padding() => (written - skipped_padding).max(header_count),
+            _ => written - skipped_padding,
+        }
+    }
+
+    /// Move as many whole packets as fit into the next CDP to the inner
+    /// [`cea708_types::CCDataWriter`].
+    fn queue_whole_packets(&mut self) {
+        let (n_packets, cc_count) = self.whole_packets_for_next_cdp();
+        for packet in self.packets.drain(..n_packets) {
+            self.cc_data.push_packet(packet);
+        }
+        self.cea708_pending_count += cc_count;
+    }
+
+    /// The cc_data section of the next CDP.  Queued data is only taken once per CDP.
+    fn cc_data_section(&mut self) -> &[u8] {
+        if self.pending_cc_data.is_none() {
+            self.pending_cc_data = self.raw_cc_data.take();
+        }
+        if self.pending_cc_data.is_none() {
+            self.queue_whole_packets();
+            let cea608_pairs = self.cea608_pair_count();
+            let mut cc_data = Vec::new();
+            self.cc_data
+                .write(self.frame_rate.into(), &mut cc_data)
+                .expect("Writing to a Vec cannot fail");
+            if cea608_pairs % 2 == 1 {
+                self.last_cea608_was_field1 = !self.last_cea608_was_field1;
+            }
+            let cea708_written = cc_data[2..]
+                .chunks_exact(3)
+                .filter(|triple| triple[0] & 0x06 == 0x06)
+                .count();
+            self.cea708_pending_count = self.cea708_pending_count.saturating_sub(cea708_written);
+            let skip_padding_type = match self.cea608_fields {
+                Cea608Fields::Both => None,
+                Cea608Fields::Field1Only => Some(0x01),
+                Cea608Fields::Field2Only => Some(0x00),
+            };
+            if let Some(cc_type) = skip_padding_type {
+                let mut triples = cc_data[2..]
+                    .chunks_exact(3)
+                    .filter(|triple| *triple != [0xf8 | cc_type, 0x80, 0x80])
+                    .flatten()
+                    .copied()
+                    .collect::<Vec<_>>();
+                let mut cc_count = triples.len() / 3;
+                if self.output_padding() {
+                    while cc_count < (cc_data[0] & 0x1f) as usize {
+                        triples.extend([0xfa, 0x00, 0x00]);
+                        cc_count += 1;
+                    }
+                }
+                cc_data.truncate(2);
+                cc_data[0] = (cc_data[0] & !0x1f) | cc_count as u8;
+                cc_data.extend(triples);
+            }
+            // cea708-types reserves the header cc_count up front which includes CEA-608 slots
+            // that are left empty when only one field has data
+            cc_data[1] = 0xe0 | ((cc_data.len() - CC_DATA_HEADER_LEN) / 3) as u8;
+            cc_data[0] = CC_DATA_ID;
+            self.pending_cc_data = Some(cc_data);
+        }
+        let cc_data = self.pending_cc_data.as_mut().unwrap();
+        cc_data[1] = self.ccdata_marker_bits | (cc_data[1] & 0x1f);
+        cc_data
+    }
+
+    fn packet_len(&self) -> usize {
+        let mut len = HEADER_LEN;
+        if self.time_code.is_some() {
+            len += TIME_CODE_SECTION_LEN;
+        }
+        let cc_count = self.next_cc_count();
+        if cc_count > 0 {
+            len += CC_DATA_HEADER_LEN + cc_count * 3;
+        }
+        len += FOOTER_LEN;
+        len
+    }
+}
+
+/// A builder for a [`CDPWriter`]
+#[derive(Debug)]
+pub struct CDPWriterBuilder {
+    frame_rate: Framerate,
+    output_padding: bool,
+    output_cea608_padding: Option<bool>,
+    auto_sequence: bool,
+    caption_service_active: bool,
+    sequence_count: u16,
+}
+
+impl CDPWriterBuilder {
+    fn new(frame_rate: Framerate) -> Self {
+        Self {
+            frame_rate,
+            output_padding: false,
+            output_cea608_padding: None,
+            auto_sequence: false,
+            caption_service_active: false,
+            sequence_count: 0,
+        }
+    }
+
+    /// See [`CDPWriter::set_output_padding`]
+    pub fn padding(mut self, output_padding: bool) -> Self {
+        self.output_padding = output_padding;
+        self
+    }
+
+    /// See [`CDPWriter::set_output_cea608_padding`].  Defaults to the value of
+    /// [`CDPWriterBuilder::padding`].
+    pub fn cea608_padding(mut self, output_cea608_padding: bool) -> Self {
+        self.output_cea608_padding = Some(output_cea608_padding);
+        self
+    }
+
+    /// See [`CDPWriter::set_auto_sequence`]
+    pub fn auto_sequence(mut self, auto_sequence: bool) -> Self {
+        self.auto_sequence = auto_sequence;
+        self
+    }
+
+    /// See [`CDPWriter::set_caption_service_active`]
+    pub fn caption_service_active(mut self, caption_service_active: bool) -> Self {
+        self.caption_service_active = caption_service_active;
+        self
+    }
+
+    /// See [`CDPWriter::set_sequence_count`]
+    pub fn sequence_count(mut self, sequence_count: u16) -> Self {
+        self.sequence_count = sequence_count;
+        self
+    }
+
+    /// Create the [`CDPWriter`]
+    pub fn build(self) -> CDPWriter {
+        let mut writer = CDPWriter::new(self.frame_rate);
+        writer.set_output_padding(self.output_padding);
+        if let Some(output_cea608_padding) = self.output_cea608_padding {
+            writer.set_output_cea608_padding(output_cea608_padding);
+        }
+        writer.set_auto_sequence(self.auto_sequence);
+        writer.set_caption_service_active(self.caption_service_active);
+        writer.set_sequence_count(self.sequence_count);
+        writer
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tests::*;
+    use cea708_types::{tables, Cea608, DTVCCPacket, Service};
+    use std::time::Duration;
+
+    #[derive(Debug)]
+    struct ServiceData<'a> {
+        service_no: u8,
+        codes: &'a [tables::Code],
+    }
+
+    #[derive(Debug)]
+    struct CCPacketData<'a> {
+        sequence_no: u8,
+        services: &'a [ServiceData<'a>],
+    }
+
+    #[derive(Debug)]
+    struct CDPPacketData<'a> {
+        data: &'a [u8],
+        sequence_count: u16,
+        time_code: Option<TimeCode>,
+        packets: &'a [CCPacketData<'a>],
+        cea608: &'a [Cea608],
+    }
+
+    #[derive(Debug)]
+    struct TestCCData<'a> {
+        framerate: Framerate,
+        cdp_data: &'a [CDPPacketData<'a>],
+    }
+
+    static PARSE_CDP: [TestCCData; 4] = [
+        // simple packet with cc_data and a time code
+        TestCCData {
+            framerate: FRAMERATES[2],
+            cdp_data: &[CDPPacketData {
+                data: &[
+                    0x96, // magic
+                    0x69,
+                    0x18,               // cdp_len
+                    0x3f,               // framerate
+                    0x80 | 0x40 | 0x01, // flags
+                    0x12,               // sequence counter
+                    0x34,
+                    0x71,        // time code id
+                    0xc0 | 0x17, // hours
+                    0x80 | 0x59, // minutes
+                    0x80 | 0x57, // seconds
+                    0x80 | 0x18, // frames
+                    0x72,        // cc_data id
+                    0xe0 | 0x02, // cc_count
+                    0xFF,
+                    0x02,
+                    0x21,
+                    0xFE,
                     0x41,
                     0x00,
                     0x74, // cdp footer
@@ -768,36 +3009,1410 @@ mod test {
     ];
 
     #[test]
-    fn cdp_parse() {
+    fn cdp_parse() {
+        test_init_log();
+        for (i, test_data) in PARSE_CDP.iter().enumerate() {
+            info!("parsing {i}: {test_data:?}");
+            let mut parser = CDPParser::new();
+            for cdp in test_data.cdp_data.iter() {
+                parser.parse(cdp.data).unwrap();
+                assert_eq!(parser.time_code(), cdp.time_code);
+                assert_eq!(parser.sequence(), cdp.sequence_count);
+                assert_eq!(parser.framerate(), Some(test_data.framerate));
+                let mut expected_packet_iter = cdp.packets.iter();
+                while let Some(packet) = parser.pop_packet() {
+                    let expected = expected_packet_iter.next().unwrap();
+                    assert_eq!(expected.sequence_no, packet.sequence_no());
+                    let services = packet.services();
+                    let mut expected_service_iter = expected.services.iter();
+                    for parsed_service in services.iter() {
+                        let expected_service = expected_service_iter.next().unwrap();
+                        assert_eq!(parsed_service.number(), expected_service.service_no);
+                        assert_eq!(expected_service.codes, parsed_service.codes());
+                    }
+                    assert!(expected_service_iter.next().is_none());
+                }
+                assert_eq!(parser.cea608().unwrap_or(&[]), cdp.cea608);
+                assert!(expected_packet_iter.next().is_none());
+            }
+            assert!(parser.pop_packet().is_none());
+        }
+    }
+
+    #[test]
+    fn checksum_matches_test_vectors() {
+        test_init_log();
+        for test_data in PARSE_CDP.iter().chain(WRITE_CDP.iter()) {
+            for cdp in test_data.cdp_data.iter() {
+                let (checksum, data) = cdp.data.split_last().unwrap();
+                assert_eq!(cdp_checksum(data), *checksum);
+                assert_eq!(verify_checksum(cdp.data), Ok(()));
+                let mut corrupt = cdp.data.to_vec();
+                corrupt[4] ^= 0x01;
+                assert_eq!(
+                    verify_checksum(&corrupt),
+                    Err(ParserError::ChecksumFailed {
+                        expected: cdp_checksum(&corrupt[..data.len()]),
+                        actual: *checksum
+                    })
+                );
+            }
+        }
+        assert_eq!(
+            verify_checksum(&[0x96, 0x69, 0x0b]),
+            Err(ParserError::LengthMismatch {
+                expected: 11,
+                actual: 3
+            })
+        );
+    }
+
+    #[test]
+    fn debug_dump_sections() {
+        test_init_log();
+        let cdp = build_cdp(
+            0x4,
+            0xc3,
+            0x1234,
+            &[
+                0x71, 0xc0, 0x80, 0x01, 0x80, 0x72, 0xe2, 0xfc, 0x14, 0x20, 0xfd, 0x80, 0x80,
+            ],
+        );
+        assert_eq!(
+            debug_dump(&cdp),
+            "magic: 96 69\n\
+             cdp_length: 24\n\
+             framerate: 0x4f (30000/1001)\n\
+             flags: 0xc3 (time_code cc_data caption_service_active reserved)\n\
+             sequence: 4660\n\
+             time_code: 71 c0 80 01 80 -> 00:00:01;00 field 0\n\
+             cc_data: 72 e2 -> cc_count 2\n  \
+             fc 14 20\n  \
+             fd 80 80\n\
+             footer: sequence 4660\n\
+             checksum: 0x24 (valid)\n"
+        );
+
+        let mut bad_checksum = cdp.clone();
+        *bad_checksum.last_mut().unwrap() ^= 0xff;
+        assert!(debug_dump(&bad_checksum).ends_with(
+            "footer: sequence 4660\nerror: checksum 0xdb does not match calculated 0x24\n"
+        ));
+
+        let mut bad_section = cdp.clone();
+        bad_section[12] = 0x73;
+        assert!(debug_dump(&bad_section).ends_with(
+            "time_code: 71 c0 80 01 80 -> 00:00:01;00 field 0\n\
+             error: expected cc_data section, found id 0x73\n"
+        ));
+
+        let unknown_framerate = build_cdp(0x0, 0x43, 0x10, &[0x72, 0xe1, 0xfc, 0x94, 0x20]);
+        assert_eq!(
+            debug_dump(&unknown_framerate),
+            "magic: 96 69\n\
+             cdp_length: 16\n\
+             framerate: 0x0f (unknown)\n\
+             flags: 0x43 (cc_data caption_service_active reserved)\n\
+             sequence: 16\n\
+             cc_data: 72 e1 -> cc_count 1\n  \
+             fc 94 20\n\
+             footer: sequence 16\n\
+             checksum: 0x08 (valid)\n"
+        );
+
+        assert_eq!(
+            debug_dump(&cdp[..2]),
+            "magic: 96 69\nerror: truncated at offset 2, needed 1 bytes but only 0 available\n"
+        );
+    }
+
+    #[test]
+    fn framerate_ordering() {
+        let mut framerates = FRAMERATES.to_vec();
+        framerates.reverse();
+        framerates.sort();
+        assert_eq!(framerates, FRAMERATES);
+        assert!(Framerate::from_id(0x1).unwrap() < Framerate::from_id(0x2).unwrap());
+        assert!(Framerate::from_id(0x8).unwrap() > Framerate::from_id(0x7).unwrap());
+    }
+
+    #[test]
+    fn framerate_max_cc_count() {
+        let expected = [
+            (0x1, 25),
+            (0x2, 25),
+            (0x3, 24),
+            (0x4, 20),
+            (0x5, 20),
+            (0x6, 12),
+            (0x7, 10),
+            (0x8, 10),
+        ];
+        for (id, max_cc_count) in expected {
+            let framerate = Framerate::from_id(id).unwrap();
+            assert_eq!(framerate.max_cc_count(), max_cc_count);
+        }
+    }
+
+    #[test]
+    fn framerate_hash() {
+        let mut set = std::collections::HashSet::new();
+        set.insert(Framerate::from_id(0x4).unwrap());
+        set.insert(Framerate::from_id(0x4).unwrap());
+        assert_eq!(set.len(), 1);
+        set.insert(Framerate::from_id(0x5).unwrap());
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn framerate_cea708_conversion() {
+        for id in 0x1..=0x8 {
+            let framerate = Framerate::from_id(id).unwrap();
+            let cea708 = cea708_types::Framerate::from(framerate);
+            assert_eq!(cea708.numer(), framerate.numer());
+            assert_eq!(cea708.denom(), framerate.denom());
+            assert_eq!(Framerate::try_from(cea708), Ok(framerate));
+        }
+        assert_eq!(
+            Framerate::try_from(cea708_types::Framerate::new(60000, 2002)),
+            Framerate::from_id(0x4).ok_or(ParserError::UnknownFramerate)
+        );
+        assert_eq!(
+            Framerate::try_from(cea708_types::Framerate::new(48, 1)),
+            Err(ParserError::UnknownFramerate)
+        );
+    }
+
+    #[test]
+    fn time_code_duration_one_hour() {
+        let one_hour = TimeCode {
+            hours: 1,
+            minutes: 0,
+            seconds: 0,
+            frames: 0,
+            field: 0,
+            drop_frame: false,
+        };
+        let one_hour_drop = TimeCode {
+            drop_frame: true,
+            ..one_hour
+        };
+        let fps_30 = Framerate::from_id(0x5).unwrap();
+        let fps_2997 = Framerate::from_id(0x4).unwrap();
+        let fps_5994 = Framerate::from_id(0x7).unwrap();
+
+        let d = one_hour.to_duration(fps_30);
+        assert_eq!(d, Duration::from_secs(3600));
+        assert_eq!(TimeCode::from_duration(d, fps_30, false), one_hour);
+
+        // non drop frame time codes drift from real time
+        let d = one_hour.to_duration(fps_2997);
+        assert_eq!(d, Duration::from_millis(3_603_600));
+        assert_eq!(TimeCode::from_duration(d, fps_2997, false), one_hour);
+
+        // drop frame time codes track real time
+        for framerate in [fps_2997, fps_5994] {
+            let d = one_hour_drop.to_duration(framerate);
+            assert!(Duration::from_secs(3600) - d < Duration::from_millis(4));
+            assert_eq!(TimeCode::from_duration(d, framerate, true), one_hour_drop);
+        }
+    }
+
+    #[test]
+    fn time_code_duration_roundtrip() {
+        for framerate in FRAMERATES.iter() {
+            for drop_frame in [false, true] {
+                for frame_number in (0..200_000).step_by(7) {
+                    let tc = TimeCode::from_frame_number(frame_number, *framerate, drop_frame);
+                    assert_eq!(tc.frame_number(*framerate), frame_number);
+                    let d = tc.to_duration(*framerate);
+                    assert_eq!(TimeCode::from_duration(d, *framerate, drop_frame), tc);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn time_code_display() {
+        let tc = TimeCode {
+            hours: 1,
+            minutes: 2,
+            seconds: 3,
+            frames: 4,
+            field: 0,
+            drop_frame: false,
+        };
+        assert_eq!(tc.to_string(), "01:02:03:04");
+        let tc = TimeCode {
+            drop_frame: true,
+            ..tc
+        };
+        assert_eq!(tc.to_string(), "01:02:03;04");
+    }
+
+    #[test]
+    fn time_code_analyze() {
+        let tc = |minutes, seconds, frames, field, drop_frame| TimeCode {
+            hours: 0,
+            minutes,
+            seconds,
+            frames,
+            field,
+            drop_frame,
+        };
+        let framerate = Framerate::from_id(0x6).unwrap();
+        let analysis = tc(0, 1, 2, 1, false).analyze(framerate);
+        assert_eq!(analysis.frame_number(), 55);
+        assert_eq!(analysis.duration(), std::time::Duration::from_millis(1100));
+        assert!(analysis.is_valid());
+        // frame out of range
+        assert!(!tc(0, 0, 25, 0, false).analyze(framerate).is_valid());
+        // no drop frame time codes at 50fps
+        assert!(!tc(0, 0, 0, 0, true).analyze(framerate).is_valid());
+
+        let framerate = Framerate::from_id(0x7).unwrap();
+        assert!(tc(1, 0, 0, 0, false).analyze(framerate).is_valid());
+        assert!(tc(1, 0, 2, 0, true).analyze(framerate).is_valid());
+        assert!(tc(10, 0, 0, 0, true).analyze(framerate).is_valid());
+        // skipped drop frame time code
+        let analysis = tc(1, 0, 1, 1, true).analyze(framerate);
+        assert!(!analysis.is_valid());
+    }
+
+    #[test]
+    fn time_code_for_framerate() {
+        let ntsc = Framerate::from_id(0x4).unwrap();
+        let tc = TimeCode::for_framerate(0, 1, 0, 2, false, ntsc).unwrap();
+        assert!(tc.drop_frame());
+        assert_eq!(tc.to_string(), "00:01:00;02");
+        assert_eq!(
+            (
+                tc.hours(),
+                tc.minutes(),
+                tc.seconds(),
+                tc.frames(),
+                tc.field()
+            ),
+            (0, 1, 0, 2, false)
+        );
+        for frames in [0, 1] {
+            assert_eq!(
+                TimeCode::for_framerate(0, 1, 0, frames, false, ntsc),
+                Err(ParserError::InvalidTimeCode)
+            );
+        }
+        assert!(TimeCode::for_framerate(0, 0, 59, 29, false, ntsc).is_ok());
+        assert!(TimeCode::for_framerate(0, 10, 0, 0, false, ntsc).is_ok());
+        assert!(TimeCode::for_framerate(0, 1, 1, 0, false, ntsc).is_ok());
+        assert_eq!(
+            TimeCode::for_framerate(0, 0, 0, 30, false, ntsc),
+            Err(ParserError::InvalidTimeCode)
+        );
+
+        // 60000/1001 time codes count frame pairs
+        let ntsc60 = Framerate::from_id(0x7).unwrap();
+        let tc = TimeCode::for_framerate(0, 1, 0, 2, true, ntsc60).unwrap();
+        assert!(tc.drop_frame());
+        assert!(tc.field());
+        assert_eq!(
+            TimeCode::for_framerate(0, 1, 0, 1, true, ntsc60),
+            Err(ParserError::InvalidTimeCode)
+        );
+
+        let pal = Framerate::from_id(0x3).unwrap();
+        let tc = TimeCode::for_framerate(0, 1, 0, 0, false, pal).unwrap();
+        assert!(!tc.drop_frame());
+        assert_eq!(
+            TimeCode::for_framerate(0, 0, 0, 25, false, pal),
+            Err(ParserError::InvalidTimeCode)
+        );
+        assert_eq!(
+            TimeCode::for_framerate(24, 0, 0, 0, false, pal),
+            Err(ParserError::InvalidTimeCode)
+        );
+        assert!(
+            !TimeCode::for_framerate(0, 1, 0, 0, false, Framerate::from_id(0x5).unwrap())
+                .unwrap()
+                .drop_frame()
+        );
+    }
+
+    #[test]
+    fn time_code_wire_roundtrip() {
+        for hours in 0..24 {
+            for minutes in (0..60).step_by(7) {
+                for seconds in (0..60).step_by(11) {
+                    for frames in 0..30 {
+                        for field in 0..2 {
+                            for drop_frame in [false, true] {
+                                let time_code = TimeCode {
+                                    hours,
+                                    minutes,
+                                    seconds,
+                                    frames,
+                                    field,
+                                    drop_frame,
+                                };
+                                let wire = time_code.to_wire();
+                                assert_eq!(TimeCode::from_wire(wire), Ok(time_code));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        let wire = [0x71, 0xc1, 0x82, 0x03, 0x04];
+        assert_eq!(
+            TimeCode::from_wire([0x72, 0xc1, 0x82, 0x03, 0x04]),
+            Err(ParserError::BadSectionId {
+                expected: TIME_CODE_ID,
+                found: 0x72,
+                offset: 0
+            })
+        );
+        for (i, bit) in [(1, 0x40), (2, 0x80)] {
+            let mut invalid = wire;
+            invalid[i] &= !bit;
+            assert_eq!(
+                TimeCode::from_wire(invalid),
+                Err(ParserError::InvalidFixedBits)
+            );
+        }
+        let mut invalid = wire;
+        invalid[4] |= 0x40;
+        assert_eq!(
+            TimeCode::from_wire(invalid),
+            Err(ParserError::InvalidFixedBits)
+        );
+    }
+
+    #[test]
+    fn time_code_ordering() {
+        let tc = |hours, minutes, seconds, frames, field| TimeCode {
+            hours,
+            minutes,
+            seconds,
+            frames,
+            field,
+            drop_frame: false,
+        };
+        let mut time_codes = vec![
+            tc(1, 0, 0, 0, 0),
+            tc(0, 0, 1, 0, 0),
+            tc(0, 0, 0, 1, 1),
+            tc(0, 59, 59, 29, 0),
+            tc(0, 0, 0, 1, 0),
+            tc(0, 0, 0, 0, 0),
+        ];
+        time_codes.sort();
+        assert_eq!(
+            time_codes,
+            [
+                tc(0, 0, 0, 0, 0),
+                tc(0, 0, 0, 1, 0),
+                tc(0, 0, 0, 1, 1),
+                tc(0, 0, 1, 0, 0),
+                tc(0, 59, 59, 29, 0),
+                tc(1, 0, 0, 0, 0),
+            ]
+        );
+        let drop_frame = TimeCode {
+            drop_frame: true,
+            ..tc(0, 0, 0, 0, 0)
+        };
+        assert!(tc(0, 0, 0, 0, 0) < drop_frame);
+        assert!(drop_frame < tc(0, 0, 0, 1, 0));
+    }
+
+    #[test]
+    fn cdp_section_roundtrip() {
+        for id in 0..=u8::MAX {
+            match CdpSection::try_from(id) {
+                Ok(section) => {
+                    assert!((TIME_CODE_ID..=FUTURE_SECTION_ID_END).contains(&id));
+                    assert_eq!(u8::from(section), id);
+                }
+                Err(e) => {
+                    assert!(!(TIME_CODE_ID..=FUTURE_SECTION_ID_END).contains(&id));
+                    assert_eq!(e, ParserError::WrongMagic);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn odd_parity() {
+        for byte in 0..=u8::MAX {
+            let with_parity = set_odd_parity(byte);
+            assert_eq!(with_parity & 0x7f, byte & 0x7f);
+            assert!(check_odd_parity(with_parity));
+            assert_eq!(check_odd_parity(byte), with_parity == byte);
+            assert!(!check_odd_parity(with_parity ^ 0x80));
+        }
+    }
+
+    #[test]
+    fn flags_roundtrip() {
+        for byte in 0..=u8::MAX {
+            let flags = Flags::from(byte);
+            assert_eq!(u8::from(flags), byte);
+        }
+        let mut flags = Flags::default();
+        assert_eq!(u8::from(flags), Flags::RESERVED);
+        flags.set_cc_data(true);
+        flags.set_time_code(true);
+        assert!(flags.cc_data());
+        assert!(flags.time_code());
+        assert!(!flags.svc_info());
+        assert_eq!(
+            u8::from(flags),
+            Flags::TIME_CODE_PRESENT | Flags::CC_DATA_PRESENT | Flags::RESERVED
+        );
+    }
+
+    #[test]
+    fn cdp_parse_failure_keeps_state() {
+        test_init_log();
+        let test_data = &PARSE_CDP[0];
+        let cdp = &test_data.cdp_data[0];
+        let mut parser = CDPParser::new();
+        parser.parse(cdp.data).unwrap();
+
+        let mut corrupt = cdp.data.to_vec();
+        corrupt[5] = 0x00;
+        assert!(parser.parse(&corrupt).is_err());
+        assert_eq!(parser.time_code(), cdp.time_code);
+        assert_eq!(parser.sequence(), cdp.sequence_count);
+        assert_eq!(parser.framerate(), Some(test_data.framerate));
+    }
+
+    #[test]
+    fn cdp_parser_snapshot() {
+        test_init_log();
+        let data = build_cdp(
+            4,
+            0xc3,
+            0x1234,
+            &[0x71, 0xc1, 0x82, 0x03, 0x84, 0x72, 0xe1, 0xfc, 0x94, 0x20],
+        );
+        let mut parser = CDPParser::new();
+        let empty = parser.snapshot();
+        assert_eq!(empty.framerate(), None);
+
+        parser.parse(&data).unwrap();
+        let snapshot = parser.snapshot();
+        assert_ne!(snapshot, empty);
+        assert_eq!(snapshot.framerate(), Framerate::from_id(0x4));
+        assert_eq!(snapshot.sequence(), 0x1234);
+        assert_eq!(snapshot.time_code(), parser.time_code());
+        assert_eq!(snapshot.flags(), Flags::from(0xc3));
+
+        let mut other = CDPParser::new();
+        other.parse(&data).unwrap();
+        assert_eq!(other.snapshot(), snapshot);
+        other.flush();
+        assert_eq!(other.snapshot(), empty);
+    }
+
+    #[test]
+    fn cdp_parser_clear_captions() {
+        test_init_log();
+        let data = build_cdp(
+            4,
+            0xc3,
+            0x1234,
+            &[
+                0x71, 0xc1, 0x82, 0x03, 0x84, 0x72, 0xe3, 0xfc, 0x94, 0x20, 0xff, 0x02, 0x21, 0xfe,
+                0x41, 0x00,
+            ],
+        );
+        let mut parser = CDPParser::new();
+        parser.parse(&data).unwrap();
+        assert_eq!(parser.packet_count(), 1);
+        assert_eq!(parser.cea608_count(), 1);
+        let snapshot = parser.snapshot();
+
+        let cea608 = parser.cea608_cloned();
+        while let Some(packet) = parser.pop_packet() {
+            assert_eq!(packet.services().len(), 1);
+            assert_eq!(cea608, [Cea608::Field1(0x94, 0x20)]);
+        }
+        parser.parse(&data).unwrap();
+
+        parser.clear_captions();
+        assert_eq!(parser.snapshot(), snapshot);
+        assert_eq!(parser.packet_count(), 0);
+        assert_eq!(parser.cea608(), None);
+        assert!(!parser.has_caption_data());
+        assert_eq!(parser.take_cc_data(), None);
+
+        // CEA-608 data is still handled after clearing
+        parser.parse(&data).unwrap();
+        assert_eq!(parser.cea608_count(), 1);
+    }
+
+    #[test]
+    fn cdp_parse_truncated() {
+        test_init_log();
+        let data = PARSE_CDP[0].cdp_data[0].data;
+        let mut parser = CDPParser::new();
+        assert_eq!(
+            parser.parse(&data[..2]),
+            Err(ParserError::Truncated { needed: 11 })
+        );
+        assert_eq!(
+            parser.parse(&data[..5]),
+            Err(ParserError::Truncated { needed: data.len() })
+        );
+        assert_eq!(
+            parser.parse(&data[..data.len() - 1]),
+            Err(ParserError::Truncated { needed: data.len() })
+        );
+        let mut extra = data.to_vec();
+        extra.push(0x00);
+        assert_eq!(
+            parser.parse(&extra),
+            Err(ParserError::LengthMismatch {
+                expected: data.len(),
+                actual: data.len() + 1
+            })
+        );
+    }
+
+    #[test]
+    fn cdp_parse_from_reader() {
+        test_init_log();
+        let test_data = &PARSE_CDP[0];
+        let cdp = &test_data.cdp_data[0];
+        let mut stream = cdp.data.to_vec();
+        stream.extend_from_slice(cdp.data);
+        stream.extend_from_slice(&cdp.data[..5]);
+        let mut reader = std::io::Cursor::new(stream);
+
+        let mut parser = CDPParser::new();
+        for _ in 0..2 {
+            parser.parse_from_reader(&mut reader).unwrap();
+            assert_eq!(parser.time_code(), cdp.time_code);
+            assert_eq!(parser.sequence(), cdp.sequence_count);
+            assert_eq!(parser.framerate(), Some(test_data.framerate));
+            assert!(parser.pop_packet().is_some());
+        }
+        assert_eq!(
+            parser.parse_from_reader(&mut reader),
+            Err(ParserError::Io(std::io::ErrorKind::UnexpectedEof))
+        );
+    }
+
+    #[test]
+    fn cdp_parse_sequence_count_mismatch() {
+        test_init_log();
+        let mut data = build_cdp(4, 0x43, 0x1234, &[0x72, 0xe1, 0xfc, 0x94, 0x20]);
+        let footer = data.len() - 3;
+        data[footer] = 0x43;
+        data[footer + 1] = 0x21;
+        let len = data.len();
+        data[len - 1] = cdp_checksum(&data[..len - 1]);
+        let mut parser = CDPParser::new();
+        assert_eq!(
+            parser.parse(&data),
+            Err(ParserError::SequenceCountMismatch {
+                header: 0x1234,
+                footer: 0x4321
+            })
+        );
+    }
+
+    #[test]
+    fn fix_checksum_after_edit() {
+        test_init_log();
+        let mut data = build_cdp(
+            4,
+            0xc3,
+            0x10,
+            &[0x71, 0xc0, 0x80, 0x01, 0x80, 0x72, 0xe1, 0xfc, 0x94, 0x20],
+        );
+        // change the frames of the time code to 02
+        data[11] = 0x02;
+        let mut parser = CDPParser::new();
+        assert!(matches!(
+            parser.parse(&data),
+            Err(ParserError::ChecksumFailed { .. })
+        ));
+
+        fix_checksum(&mut data);
+        parser.parse(&data).unwrap();
+        assert_eq!(parser.time_code().unwrap().frames(), 2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn fix_checksum_length_mismatch() {
+        let mut data = build_cdp(4, 0x43, 0x10, &[0x72, 0xe1, 0xfc, 0x94, 0x20]);
+        data.push(0);
+        fix_checksum(&mut data);
+    }
+
+    #[test]
+    fn empty_cdp_parses() {
+        test_init_log();
+        let mut parser = CDPParser::new();
+        for id in 0x1..=0x8 {
+            let framerate = Framerate::from_id(id).unwrap();
+            let data = empty_cdp(framerate, 0xfff0 + id as u16);
+            parser.parse(&data).unwrap();
+            assert_eq!(parser.framerate(), Some(framerate));
+            assert_eq!(parser.sequence(), 0xfff0 + id as u16);
+            assert_eq!(parser.time_code(), None);
+            assert!(parser.sections_present().cc_data());
+            assert!(!parser.has_caption_data());
+            assert!(parser.cc_data_was_padding_only());
+            assert!(parser.pop_packet().is_none());
+        }
+    }
+
+    #[test]
+    fn cdp_had_cc_data_section() {
+        test_init_log();
+        let mut parser = CDPParser::new();
+        assert!(!parser.had_cc_data_section());
+
+        parser.parse(&build_cdp(3, 0x01, 0x10, &[])).unwrap();
+        assert!(!parser.had_cc_data_section());
+        assert!(!parser.has_caption_data());
+
+        parser
+            .parse(&build_cdp(3, 0x41, 0x11, &[0x72, 0xe1, 0xfa, 0x00, 0x00]))
+            .unwrap();
+        assert!(parser.had_cc_data_section());
+        assert!(!parser.has_caption_data());
+
+        parser
+            .parse(&build_cdp(3, 0x41, 0x12, &[0x72, 0xe1, 0xfc, 0x94, 0x20]))
+            .unwrap();
+        assert!(parser.had_cc_data_section());
+        assert!(parser.has_caption_data());
+
+        parser.flush();
+        assert!(!parser.had_cc_data_section());
+    }
+
+    #[test]
+    fn sequence_delta_wrap() {
+        assert_eq!(sequence_delta(0x1234, 0x1235), 1);
+        assert_eq!(sequence_delta(0x1234, 0x1234), 0);
+        assert_eq!(sequence_delta(0x1235, 0x1234), -1);
+        assert_eq!(sequence_delta(0xffff, 0x0000), 1);
+        assert_eq!(sequence_delta(0xfffe, 0x0001), 3);
+        assert_eq!(sequence_delta(0x0000, 0xffff), -1);
+        assert_eq!(sequence_delta(0x0000, 0x7fff), 32767);
+        assert_eq!(sequence_delta(0x0000, 0x8000), -32768);
+    }
+
+    #[test]
+    fn cdp_validate() {
+        test_init_log();
+        let data = build_cdp(
+            4,
+            0x43,
+            0x10,
+            &[0x72, 0xe2, 0xfc, 0x94, 0x20, 0xff, 0x02, 0x21],
+        );
+        assert_eq!(CDPParser::validate(&data), Ok(()));
+
+        let mut corrupt = data.clone();
+        *corrupt.last_mut().unwrap() ^= 0xff;
+        let checksum = *data.last().unwrap();
+        assert_eq!(
+            CDPParser::validate(&corrupt),
+            Err(ParserError::ChecksumFailed {
+                expected: checksum,
+                actual: checksum ^ 0xff
+            })
+        );
+
+        let cea608_after_cea708 = build_cdp(
+            4,
+            0x43,
+            0x10,
+            &[0x72, 0xe2, 0xff, 0x02, 0x21, 0xfc, 0x94, 0x20],
+        );
+        assert_eq!(
+            CDPParser::validate(&cea608_after_cea708),
+            Err(ParserError::Cea608AfterCea708 { byte_pos: 3 })
+        );
+        assert_eq!(
+            CDPParser::new().parse(&cea608_after_cea708),
+            Err(ParserError::Cea608AfterCea708 { byte_pos: 3 })
+        );
+    }
+
+    #[test]
+    fn cdp_parse_recover_on_error() {
+        test_init_log();
+        // an unknown section id 0x20 after a truncated future section
+        let data = build_cdp(
+            4,
+            0x43,
+            0x10,
+            &[0x72, 0xe1, 0xfc, 0x94, 0x20, 0x75, 0x01, 0xaa, 0x20, 0xbb],
+        );
+        let mut parser = CDPParser::new();
+        assert!(!parser.recover_on_error());
+        assert_eq!(parser.parse(&data), Err(ParserError::WrongMagic));
+
+        parser.set_recover_on_error(true);
+        assert!(parser.recover_on_error());
+        parser.parse(&data).unwrap();
+        assert_eq!(parser.sequence(), 0x10);
+        assert_eq!(parser.cea608(), Some(&[Cea608::Field1(0x94, 0x20)][..]));
+
+        // the checksum is still validated
+        let mut corrupt = data.clone();
+        *corrupt.last_mut().unwrap() ^= 0xff;
+        assert!(matches!(
+            parser.parse(&corrupt),
+            Err(ParserError::ChecksumFailed { .. })
+        ));
+
+        // the footer must be at the end of the CDP
+        let mut no_footer = data.clone();
+        let footer_idx = no_footer.len() - 4;
+        no_footer[footer_idx] = 0x00;
+        let len = no_footer.len();
+        no_footer[len - 1] = cdp_checksum(&no_footer[..len - 1]);
+        assert_eq!(parser.parse(&no_footer), Err(ParserError::WrongMagic));
+    }
+
+    #[test]
+    fn split_cdps_stream() {
+        test_init_log();
+        let first = build_cdp(4, 0x43, 0x10, &[0x72, 0xe1, 0xfc, 0x94, 0x20]);
+        let second = build_cdp(4, 0x03, 0x11, &[]);
+        let mut stream = first.clone();
+        stream.extend_from_slice(&second);
+
+        let cdps = split_cdps(&stream).collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(cdps, [&first[..], &second[..]]);
+        assert_eq!(split_cdps(&[]).count(), 0);
+
+        stream.extend_from_slice(&first[..5]);
+        let mut iter = split_cdps(&stream);
+        assert_eq!(iter.next(), Some(Ok(&first[..])));
+        assert_eq!(iter.next(), Some(Ok(&second[..])));
+        assert_eq!(
+            iter.next(),
+            Some(Err(ParserError::Truncated {
+                needed: first.len()
+            }))
+        );
+        assert_eq!(iter.next(), None);
+
+        let mut iter = split_cdps(&[0x96, 0x68, 0x0b]);
+        assert_eq!(iter.next(), Some(Err(ParserError::WrongMagic)));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn cdp_unknown_framerate() {
+        test_init_log();
+        let data = build_cdp(0, 0x43, 0x10, &[0x72, 0xe1, 0xfc, 0x94, 0x20]);
+        let mut parser = CDPParser::new();
+        assert!(!parser.allow_unknown_framerate());
+        assert_eq!(parser.parse(&data), Err(ParserError::UnknownFramerate));
+
+        parser.set_allow_unknown_framerate(true);
+        parser.parse(&data).unwrap();
+        assert_eq!(parser.framerate(), None);
+        assert_eq!(parser.cea608(), Some(&[Cea608::Field1(0x94, 0x20)][..]));
+
+        let mut writer = CDPWriter::new(Framerate::from_id(0x4).unwrap());
+        writer.set_unknown_framerate(true);
+        assert!(writer.unknown_framerate());
+        writer.set_sequence_count(0x10);
+        writer.take_from_parser(&mut parser);
+        let mut written = vec![];
+        writer.write(&mut written).unwrap();
+        assert_eq!(written, data);
+    }
+
+    #[test]
+    fn cdp_parse_bad_section_id() {
+        test_init_log();
+        let mut parser = CDPParser::new();
+        // time code flag set without a time code section
+        let data = build_cdp(4, 0xc3, 0x10, &[0x72, 0xe1, 0xfc, 0x94, 0x20]);
+        assert_eq!(
+            parser.parse(&data),
+            Err(ParserError::BadSectionId {
+                expected: TIME_CODE_ID,
+                found: CC_DATA_ID,
+                offset: 7
+            })
+        );
+        // cc_data flag set without a cc_data section
+        let data = build_cdp(4, 0xc3, 0x10, &[0x71, 0xc0, 0x80, 0x01, 0x80, 0x74]);
+        assert_eq!(
+            parser.parse(&data),
+            Err(ParserError::BadSectionId {
+                expected: CC_DATA_ID,
+                found: CDP_FOOTER_ID,
+                offset: 12
+            })
+        );
+        // svc_info flag set without a svc_info section
+        let data = build_cdp(4, 0x37, 0x10, &[0x75, 0x00]);
+        assert_eq!(
+            parser.parse(&data),
+            Err(ParserError::BadSectionId {
+                expected: SVC_INFO_ID,
+                found: 0x75,
+                offset: 7
+            })
+        );
+    }
+
+    #[test]
+    fn cdp_parse_allow_trailing() {
+        test_init_log();
+        let cdp = build_cdp(4, 0x43, 0x10, &[0x72, 0xe1, 0xfc, 0x94, 0x20]);
+        let mut data = cdp.clone();
+        data.extend([0; 8]);
+        let mut parser = CDPParser::new();
+        assert!(!parser.allow_trailing());
+        assert_eq!(
+            parser.parse(&data),
+            Err(ParserError::LengthMismatch {
+                expected: cdp.len(),
+                actual: data.len()
+            })
+        );
+
+        parser.set_allow_trailing(true);
+        parser.parse(&data).unwrap();
+        assert_eq!(parser.sequence(), 0x10);
+        assert_eq!(parser.cea608(), Some(&[Cea608::Field1(0x94, 0x20)][..]));
+    }
+
+    #[test]
+    fn cdp_parse_trust_cdp_len() {
+        test_init_log();
+        let data = build_cdp(
+            4,
+            0x43,
+            0x10,
+            &[0x72, 0xe1, 0xfc, 0x94, 0x20, 0x00, 0x00, 0x00, 0x00],
+        );
+        let mut parser = CDPParser::new();
+        assert!(!parser.trust_cdp_len());
+        assert_eq!(parser.parse(&data), Err(ParserError::WrongMagic));
+
+        parser.set_trust_cdp_len(true);
+        parser.parse(&data).unwrap();
+        assert_eq!(parser.cea608(), Some(&[Cea608::Field1(0x94, 0x20)][..]));
+
+        // the checksum still covers the padding
+        let mut corrupt = data.clone();
+        corrupt[12] = 0x01;
+        assert!(matches!(
+            parser.parse(&corrupt),
+            Err(ParserError::ChecksumFailed { .. })
+        ));
+
+        // sections overrunning the footer are still an error
+        let mut overrun = build_cdp(4, 0x43, 0x10, &[0x72, 0xe1, 0xfc, 0x94, 0x20]);
+        overrun[8] = 0xe2;
+        assert!(parser.parse(&overrun).is_err());
+    }
+
+    #[test]
+    fn cdp_parser_stats() {
+        test_init_log();
+        let data = build_cdp(4, 0x43, 0x10, &[0x72, 0xe1, 0xfc, 0x94, 0x20]);
+        let mut parser = CDPParser::new();
+        assert_eq!(*parser.stats(), ParserStats::default());
+
+        parser.parse(&data).unwrap();
+        let mut corrupt = data.clone();
+        *corrupt.last_mut().unwrap() ^= 0xff;
+        parser.parse(&corrupt).unwrap_err();
+        let mut sequence_mismatch = data.clone();
+        let len = sequence_mismatch.len();
+        sequence_mismatch[len - 2] = 0x11;
+        fix_checksum(&mut sequence_mismatch);
+        parser.parse(&sequence_mismatch).unwrap_err();
+        parser.parse(&data[..data.len() - 1]).unwrap_err();
+        parser.parse(&[0x00; 16]).unwrap_err();
+
+        let stats = *parser.stats();
+        assert_eq!(stats.parsed(), 1);
+        assert_eq!(stats.checksum_failures(), 1);
+        assert_eq!(stats.sequence_mismatches(), 1);
+        assert_eq!(stats.length_mismatches(), 1);
+        assert_eq!(stats.other_errors(), 1);
+        assert_eq!(stats.errors(), 4);
+
+        parser.flush();
+        assert_eq!(*parser.stats(), stats);
+        parser.reset_stats();
+        assert_eq!(*parser.stats(), ParserStats::default());
+    }
+
+    #[test]
+    fn cdp_parse_recover_truncated_ccdata() {
+        test_init_log();
+        // cc_count of 3 with only a single triple and part of another
+        let data = build_cdp(4, 0x43, 0x10, &[0x72, 0xe3, 0xfc, 0x94, 0x20, 0xfd, 0x15]);
+        let mut parser = CDPParser::new();
+        assert!(!parser.recover_truncated_ccdata());
+        assert!(matches!(
+            parser.parse(&data),
+            Err(ParserError::LengthMismatch { .. })
+        ));
+
+        parser.set_recover_truncated_ccdata(true);
+        parser.parse(&data).unwrap();
+        assert_eq!(parser.cea608(), Some(&[Cea608::Field1(0x94, 0x20)][..]));
+        assert_eq!(
+            parser.take_cc_data(),
+            Some(vec![0xc1, 0xff, 0xfc, 0x94, 0x20])
+        );
+
+        // a complete CDP is unaffected
+        let data = build_cdp(4, 0x43, 0x11, &[0x72, 0xe1, 0xfc, 0x94, 0x20]);
+        parser.parse(&data).unwrap();
+        assert_eq!(parser.sequence(), 0x11);
+    }
+
+    #[test]
+    fn cdp_strict_svc_info() {
+        test_init_log();
+        // svc_info present, start and complete with a svc_count of 0
+        let data = build_cdp(4, 0x37, 0x10, &[0x73, 0xe0]);
+        let mut parser = CDPParser::new();
+        assert!(!parser.strict_svc_info());
+        parser.parse(&data).unwrap();
+
+        parser.set_strict_svc_info(true);
+        assert_eq!(parser.parse(&data), Err(ParserError::EmptyServiceInfo));
+
+        let data = build_cdp(
+            4,
+            0x37,
+            0x11,
+            &[0x73, 0xe1, 0x80, b'e', b'n', b'g', 0xc1, 0x3f, 0xff],
+        );
+        parser.parse(&data).unwrap();
+        assert_eq!(parser.sequence(), 0x11);
+    }
+
+    #[test]
+    fn cdp_parse_cc_data_failure_keeps_state() {
+        test_init_log();
+        let first = build_cdp(
+            4,
+            0x43,
+            0x10,
+            &[0x72, 0xe2, 0xfc, 0x94, 0x20, 0xff, 0x02, 0x21],
+        );
+        let second = build_cdp(
+            4,
+            0x43,
+            0x11,
+            &[0x72, 0xe2, 0xfc, 0x94, 0x2c, 0xfe, 0x41, 0x00],
+        );
+        let cea608_after_cea708 = build_cdp(
+            4,
+            0x43,
+            0x12,
+            &[0x72, 0xe2, 0xff, 0x02, 0x21, 0xfc, 0x94, 0x2f],
+        );
+        let mut parser = CDPParser::new();
+        parser.parse(&first).unwrap();
+        parser.parse(&second).unwrap();
+        assert!(parser.has_caption_data());
+        assert_eq!(parser.packet_count(), 1);
+        assert_eq!(
+            parser.parse(&cea608_after_cea708),
+            Err(ParserError::Cea608AfterCea708 { byte_pos: 3 })
+        );
+        assert!(parser.has_caption_data());
+        assert!(!parser.cc_data_was_padding_only());
+        assert_eq!(parser.sequence(), 0x11);
+        assert_eq!(parser.cea608(), Some(&[Cea608::Field1(0x94, 0x2c)][..]));
+        assert_eq!(parser.packet_count(), 1);
+        assert_eq!(parser.pop_packet().unwrap().services().len(), 1);
+    }
+
+    #[test]
+    fn cdp_parse_all() {
+        test_init_log();
+        let first = build_cdp(
+            4,
+            0xc3,
+            0x10,
+            &[
+                0x71, 0xc0, 0x80, 0x01, 0x80, 0x72, 0xe2, 0xfc, 0x94, 0x20, 0xff, 0x02, 0x21,
+            ],
+        );
+        let second = build_cdp(
+            4,
+            0xc3,
+            0x11,
+            &[
+                0x71, 0xc0, 0x80, 0x01, 0x81, 0x72, 0xe2, 0xfc, 0x94, 0x2c, 0xfe, 0x41, 0x00,
+            ],
+        );
+        let mut stream = first.clone();
+        stream.extend_from_slice(&second);
+        stream.extend_from_slice(&first[..5]);
+
+        let mut parser = CDPParser::new();
+        assert_eq!(parser.packet_count(), 0);
+        assert_eq!(parser.cea608_count(), 0);
+        assert_eq!(parser.framerate_id(), None);
+        assert_eq!(parser.parse_all(&stream), Ok(first.len() + second.len()));
+        assert_eq!(parser.framerate_id(), Some(0x4));
+        assert_eq!(parser.packet_count(), 1);
+        assert_eq!(parser.cea608_count(), 2);
+        assert_eq!(parser.sequence(), 0x11);
+        assert_eq!(parser.time_code().unwrap().to_string(), "00:00:01;01");
+        assert_eq!(
+            parser.cea608().unwrap(),
+            [Cea608::Field1(0x94, 0x20), Cea608::Field1(0x94, 0x2c)]
+        );
+        let packet = parser.pop_packet().unwrap();
+        assert_eq!(packet.services().len(), 1);
+        assert!(parser.pop_packet().is_none());
+        assert_eq!(parser.packet_count(), 0);
+
+        assert_eq!(parser.parse_all(&first[..5]), Ok(0));
+        assert_eq!(parser.parse_all(&first[..1]), Ok(0));
+        assert_eq!(parser.parse_all(&[]), Ok(0));
+        // garbage that is shorter than its byte 2 is not an incomplete CDP
+        assert_eq!(
+            parser.parse_all(&[0x12, 0x34, 0xff]),
+            Err(ParserError::WrongMagic)
+        );
+        assert_eq!(
+            parser.parse_all(&[0x96, 0x00]),
+            Err(ParserError::WrongMagic)
+        );
+        let mut trailing_garbage = second.clone();
+        trailing_garbage.extend_from_slice(&[0x00, 0x01]);
+        assert_eq!(
+            parser.parse_all(&trailing_garbage),
+            Err(ParserError::WrongMagic)
+        );
+        assert_eq!(parser.sequence(), 0x11);
+
+        // no CEA-608 in the parsed CDP
+        parser
+            .parse(&build_cdp(4, 0x41, 0x12, &[0x72, 0xe1, 0xfa, 0x00, 0x00]))
+            .unwrap();
+        assert_eq!(parser.cea608(), None);
+
+        let mut corrupt = first.clone();
+        corrupt.extend_from_slice(&second);
+        *corrupt.last_mut().unwrap() ^= 0xff;
+        assert!(matches!(
+            parser.parse_all(&corrupt),
+            Err(ParserError::ChecksumFailed { .. })
+        ));
+        assert_eq!(parser.sequence(), 0x10);
+    }
+
+    #[test]
+    fn cdp_parse_strict_cea608() {
+        test_init_log();
+        let field2_only = build_cdp(3, 0x41, 0, &[0x72, 0xe1, 0xFD, 0x94, 0x20]);
+        let both_fields = build_cdp(
+            3,
+            0x41,
+            0,
+            &[0x72, 0xe2, 0xFC, 0x80, 0x80, 0xFD, 0x94, 0x20],
+        );
+
+        let mut parser = CDPParser::new();
+        assert!(!parser.strict_cea608());
+        parser.parse(&field2_only).unwrap();
+        parser.parse(&both_fields).unwrap();
+
+        parser.set_strict_cea608(true);
+        assert_eq!(
+            parser.parse(&field2_only),
+            Err(ParserError::Cea608FieldOrder)
+        );
+        parser.parse(&both_fields).unwrap();
+    }
+
+    #[test]
+    fn parse_write_roundtrip() {
         test_init_log();
         for (i, test_data) in PARSE_CDP.iter().enumerate() {
-            info!("parsing {i}: {test_data:?}");
             let mut parser = CDPParser::new();
+            let mut writer = CDPWriter::new(test_data.framerate);
             for cdp in test_data.cdp_data.iter() {
+                let flags = Flags::from(cdp.data[4]);
+                // service information and future sections are not written
+                if !flags.cc_data() || flags.svc_info() {
+                    continue;
+                }
+                info!("roundtripping {i}: {cdp:?}");
                 parser.parse(cdp.data).unwrap();
-                assert_eq!(parser.time_code(), cdp.time_code);
-                assert_eq!(parser.sequence(), cdp.sequence_count);
-                assert_eq!(parser.framerate(), Some(test_data.framerate));
-                let mut expected_packet_iter = cdp.packets.iter();
-                while let Some(packet) = parser.pop_packet() {
-                    let expected = expected_packet_iter.next().unwrap();
-                    assert_eq!(expected.sequence_no, packet.sequence_no());
-                    let services = packet.services();
-                    let mut expected_service_iter = expected.services.iter();
-                    for parsed_service in services.iter() {
-                        let expected_service = expected_service_iter.next().unwrap();
-                        assert_eq!(parsed_service.number(), expected_service.service_no);
-                        assert_eq!(expected_service.codes, parsed_service.codes());
-                    }
-                    assert!(expected_service_iter.next().is_none());
+                for packet in parser.drain_packets() {
+                    writer.push_packet(packet);
                 }
-                assert_eq!(parser.cea608().unwrap_or(&[]), cdp.cea608);
-                assert!(expected_packet_iter.next().is_none());
+                assert!(parser.pop_packet().is_none());
+                writer.set_time_code(parser.time_code());
+                writer.set_sequence_count(parser.sequence());
+                let mut written = vec![];
+                writer.write(&mut written).unwrap();
+                assert_eq!(cdp.data, &written);
             }
-            assert!(parser.pop_packet().is_none());
         }
     }
 
+    #[test]
+    fn reserved_flag_roundtrip() {
+        test_init_log();
+        let data = build_cdp(3, 0x40, 0x10, &[0x72, 0xe1, 0xFE, 0x41, 0x00]);
+        let mut parser = CDPParser::new();
+        parser.parse(&data).unwrap();
+        assert!(!parser.reserved_flag());
+
+        let mut writer = CDPWriter::new(parser.framerate().unwrap());
+        assert!(writer.reserved_flag());
+        writer.set_reserved_flag(parser.reserved_flag());
+        writer.set_sequence_count(parser.sequence());
+        let mut written = vec![];
+        writer.write(&mut written).unwrap();
+        // nothing was queued so no cc_data section is written
+        assert_eq!(written[4], 0x00);
+        parser.parse(&written).unwrap();
+        assert!(!parser.reserved_flag());
+    }
+
+    #[cfg(feature = "conformance-testing")]
+    #[test]
+    fn writer_ccdata_marker_bits() {
+        test_init_log();
+        let mut writer = CDPWriter::new(Framerate::from_id(0x3).unwrap());
+        writer.push_cea608(cea708_types::Cea608::Field1(0x94, 0x20));
+        writer.set_ccdata_marker_bits(0x41);
+        let mut written = vec![];
+        writer.write(&mut written).unwrap();
+        assert_eq!(written[7], 0x72);
+        assert_eq!(written[8] & 0xe0, 0x40);
+        let mut parser = CDPParser::new();
+        assert_eq!(parser.parse(&written), Err(ParserError::InvalidFixedBits));
+
+        writer.set_ccdata_marker_bits(0xe0);
+        writer.push_cea608(cea708_types::Cea608::Field1(0x94, 0x20));
+        let mut written = vec![];
+        writer.write(&mut written).unwrap();
+        parser.parse(&written).unwrap();
+    }
+
+    #[test]
+    fn writer_take_from_parser() {
+        test_init_log();
+        let data = build_cdp(
+            4,
+            0xc3,
+            0x1234,
+            &[
+                0x71, 0xc1, 0x82, 0x03, 0x84, 0x72, 0xe4, 0xfc, 0x94, 0x20, 0xfd, 0x41, 0x42, 0xff,
+                0x02, 0x21, 0xfe, 0x41, 0x00,
+            ],
+        );
+        let mut parser = CDPParser::new();
+        parser.parse(&data).unwrap();
+
+        let mut writer = CDPWriter::new(parser.framerate().unwrap());
+        writer.take_from_parser(&mut parser);
+        assert_eq!(parser.packet_count(), 0);
+        assert_eq!(parser.cea608_count(), 0);
+        let mut written = vec![];
+        writer.write(&mut written).unwrap();
+        assert_eq!(written, data);
+    }
+
+    #[test]
+    fn writer_push_cea608_pair() {
+        test_init_log();
+        let mut writer = CDPWriter::new(Framerate::from_id(0x4).unwrap());
+        writer.push_cea608_pair((0x14, 0x20), (0x41, 0xc2));
+        let mut written = vec![];
+        writer.write(&mut written).unwrap();
+        assert_eq!(
+            &written[7..written.len() - 4],
+            [0x72, 0xe2, 0xfc, 0x94, 0x20, 0xfd, 0xc1, 0xc2]
+        );
+        for triple in written[9..written.len() - 4].chunks_exact(3) {
+            assert_eq!(triple[1].count_ones() % 2, 1);
+            assert_eq!(triple[2].count_ones() % 2, 1);
+        }
+    }
+
+    #[test]
+    fn write_time_code_only() {
+        test_init_log();
+        let framerate = Framerate::from_id(0x4).unwrap();
+        let time_code = TimeCode::for_framerate(1, 2, 3, 4, false, framerate).unwrap();
+        let mut writer = CDPWriter::new(framerate);
+        writer.set_time_code(Some(time_code));
+        let mut written = vec![];
+        writer.write(&mut written).unwrap();
+        assert_eq!(written.len(), MIN_PACKET_LEN + TIME_CODE_SECTION_LEN);
+        assert_eq!(written[4], Flags::TIME_CODE_PRESENT | Flags::RESERVED);
+
+        let mut parser = CDPParser::new();
+        parser.parse(&written).unwrap();
+        assert_eq!(parser.time_code(), Some(time_code));
+        assert!(!parser.sections_present().cc_data());
+        assert_eq!(parser.take_cc_data(), None);
+    }
+
+    #[test]
+    fn has_caption_data() {
+        test_init_log();
+        let mut parser = CDPParser::new();
+        assert!(!parser.has_caption_data());
+
+        let padding = build_cdp(
+            4,
+            0x43,
+            0x10,
+            &[
+                0x72, 0xe4, 0xf8, 0x80, 0x80, 0xfc, 0x80, 0x80, 0xf9, 0x80, 0x80, 0xfa, 0x00, 0x00,
+            ],
+        );
+        parser.parse(&padding).unwrap();
+        assert!(!parser.has_caption_data());
+        assert!(parser.cc_data_was_padding_only());
+        assert!(parser.pop_packet().is_none());
+
+        let cea608 = build_cdp(4, 0x43, 0x11, &[0x72, 0xe1, 0xfc, 0x94, 0x20]);
+        parser.parse(&cea608).unwrap();
+        assert!(parser.has_caption_data());
+        assert!(!parser.cc_data_was_padding_only());
+
+        let no_cc_data = build_cdp(4, 0x03, 0x12, &[]);
+        parser.parse(&no_cc_data).unwrap();
+        assert!(!parser.has_caption_data());
+        assert!(!parser.cc_data_was_padding_only());
+
+        let cea708 = build_cdp(
+            4,
+            0x43,
+            0x13,
+            &[0x72, 0xe2, 0xff, 0x02, 0x21, 0xfe, 0x41, 0x00],
+        );
+        parser.parse(&cea708).unwrap();
+        assert!(parser.has_caption_data());
+        assert_eq!(parser.pop_packet().unwrap().services().len(), 1);
+    }
+
+    #[test]
+    fn cdp_sections_present() {
+        test_init_log();
+        let mut parser = CDPParser::new();
+        assert_eq!(parser.sections_present(), Flags::default());
+
+        let data = build_cdp(
+            4,
+            0xf7,
+            0x10,
+            &[
+                0x71, 0xc0, 0x80, 0x01, 0x80, 0x72, 0xe1, 0xfc, 0x94, 0x20, 0x73, 0xe0,
+            ],
+        );
+        parser.parse(&data).unwrap();
+        let flags = parser.sections_present();
+        assert!(flags.time_code());
+        assert!(flags.cc_data());
+        assert!(flags.svc_info());
+        assert!(flags.svc_info_start());
+        assert!(!flags.svc_info_change());
+        assert!(flags.svc_info_complete());
+        assert!(flags.caption_service_active());
+        assert!(flags.reserved());
+        assert_eq!(parser.flags_byte(), 0xf7);
+        assert_eq!(parser.service_info_start(), Some(true));
+        assert_eq!(parser.service_info_change(), Some(false));
+        assert_eq!(parser.service_info_complete(), Some(true));
+
+        let data = build_cdp(4, 0x5d, 0x11, &[0x72, 0xe1, 0xfc, 0x94, 0x20]);
+        parser.parse(&data).unwrap();
+        assert!(!parser.sections_present().svc_info());
+        assert_eq!(parser.service_info_start(), None);
+        assert_eq!(parser.service_info_change(), None);
+        assert_eq!(parser.service_info_complete(), None);
+    }
+
+    #[test]
+    fn writer_flags_byte() {
+        test_init_log();
+        let mut writer = CDPWriter::new(Framerate::from_id(0x4).unwrap());
+        assert_eq!(writer.flags_byte(), 0x01);
+        writer.set_caption_service_active(true);
+        writer.push_cea608(Cea608::Field1(0x94, 0x20));
+        // data pushed after a preview is still written in the next CDP
+        assert_eq!(writer.flags_byte(), 0x43);
+        let mut written = vec![];
+        writer.write(&mut written).unwrap();
+        assert_eq!(written[4], 0x43);
+
+        writer.set_time_code(Some(TimeCode {
+            hours: 1,
+            minutes: 2,
+            seconds: 3,
+            frames: 4,
+            field: 0,
+            drop_frame: false,
+        }));
+        assert_eq!(writer.flags_byte(), 0x83);
+        writer.push_cea608(Cea608::Field1(0x94, 0x20));
+        writer.push_cea608(Cea608::Field2(0x15, 0x20));
+        assert_eq!(writer.flags_byte(), 0xc3);
+        written.clear();
+        writer.write(&mut written).unwrap();
+        assert_eq!(written[4], 0xc3);
+
+        // the time code is kept until cleared
+        assert_eq!(writer.flags_byte(), 0x83);
+        writer.clear_time_code();
+        assert_eq!(writer.flags_byte(), 0x03);
+        written.clear();
+        writer.write(&mut written).unwrap();
+        assert_eq!(written[4], 0x03);
+    }
+
+    #[test]
+    fn caption_service_inactive_with_data() {
+        test_init_log();
+        let data = build_cdp(3, 0x41, 0x10, &[0x72, 0xe1, 0xfc, 0x94, 0x20]);
+        let mut parser = CDPParser::new();
+        parser.parse(&data).unwrap();
+        assert!(!parser.caption_service_active());
+        assert_eq!(parser.cea608(), Some(&[Cea608::Field1(0x94, 0x20)][..]));
+
+        let mut writer = CDPWriter::new(parser.framerate().unwrap());
+        assert!(!writer.caption_service_active());
+        writer.set_sequence_count(parser.sequence());
+        writer.push_cea608(Cea608::Field1(0x94, 0x20));
+        let mut written = vec![];
+        writer.write(&mut written).unwrap();
+        assert_eq!(written, data);
+
+        writer.set_caption_service_active(true);
+        written.clear();
+        writer.write(&mut written).unwrap();
+        parser.parse(&written).unwrap();
+        assert!(parser.caption_service_active());
+    }
+
     static WRITE_CDP: [TestCCData; 2] = [
         // simple packet with a single service and single code
         TestCCData {
@@ -916,6 +4531,536 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn packet_write_into_slice() {
+        test_init_log();
+        let test_data = &WRITE_CDP[0];
+        let cdp_data = &test_data.cdp_data[0];
+        let mut writer = CDPWriter::new(test_data.framerate);
+        let mut pack = DTVCCPacket::new(0);
+        let mut service = Service::new(1);
+        service.push_code(&tables::Code::LatinCapitalA).unwrap();
+        pack.push_service(service).unwrap();
+        writer.push_packet(pack);
+        writer.set_time_code(cdp_data.time_code);
+        writer.set_sequence_count(cdp_data.sequence_count);
+
+        let mut buf = [0; 32];
+        assert_eq!(
+            writer.write_into(&mut buf[..10]),
+            Err(WriterError::WouldOverflow(cdp_data.data.len() - 10))
+        );
+        // the failed write must not consume any data
+        let len = writer.write_into(&mut buf).unwrap();
+        assert_eq!(cdp_data.data, &buf[..len]);
+    }
+
+    #[test]
+    fn packet_write_overflow_spans_cdps() {
+        test_init_log();
+        let framerate = Framerate::from_id(0x5).unwrap();
+        let mut writer = CDPWriter::new(framerate);
+        let mut pack = DTVCCPacket::new(0);
+        for service_no in 1..=2 {
+            let mut service = Service::new(service_no);
+            for _ in 0..30 {
+                service.push_code(&tables::Code::LatinCapitalA).unwrap();
+            }
+            pack.push_service(service).unwrap();
+        }
+        // 63 bytes of packet data requires 32 cc_data triples, more than the 20 allowed at 30fps
+        writer.push_packet(pack);
+
+        let mut parser = CDPParser::new();
+        let mut first = vec![];
+        writer.write(&mut first).unwrap();
+        assert_eq!(
+            first.len(),
+            7 + 2 + framerate.max_cc_count() as usize * 3 + 4
+        );
+        parser.parse(&first).unwrap();
+        assert!(parser.pop_packet().is_none());
+
+        let mut second = vec![];
+        writer.write(&mut second).unwrap();
+        assert_eq!(second.len(), 7 + 2 + 12 * 3 + 4);
+        parser.parse(&second).unwrap();
+        let packet = parser.pop_packet().unwrap();
+        assert_eq!(packet.services().len(), 2);
+        for service in packet.services() {
+            assert_eq!(service.codes().len(), 30);
+        }
+    }
+
+    #[test]
+    fn packet_write_whole_packets_fifo() {
+        test_init_log();
+        let framerate = Framerate::from_id(0x5).unwrap();
+        let mut writer = CDPWriter::new(framerate);
+        for seq_no in 0..3 {
+            let mut pack = DTVCCPacket::new(seq_no);
+            let mut service = Service::new(1);
+            for _ in 0..12 {
+                service.push_code(&tables::Code::LatinCapitalA).unwrap();
+            }
+            pack.push_service(service).unwrap();
+            // 14 bytes of packet data requires 7 cc_data triples, only 2 packets fit in the 20
+            // triples allowed at 30fps
+            writer.push_packet(pack);
+        }
+
+        assert_eq!(writer.queued_packet_count(), 3);
+        assert_eq!(writer.peek_next_packet().unwrap().sequence_no(), 0);
+
+        let mut parser = CDPParser::new();
+        let mut first = vec![];
+        writer.write(&mut first).unwrap();
+        assert_eq!(writer.queued_packet_count(), 1);
+        assert_eq!(writer.peek_next_packet().unwrap().sequence_no(), 2);
+        assert_eq!(first.len(), 7 + 2 + 14 * 3 + 4);
+        parser.parse(&first).unwrap();
+        assert_eq!(parser.pop_packet().unwrap().sequence_no(), 0);
+        assert_eq!(parser.pop_packet().unwrap().sequence_no(), 1);
+        assert!(parser.pop_packet().is_none());
+
+        let mut second = vec![];
+        writer.write(&mut second).unwrap();
+        assert_eq!(writer.queued_packet_count(), 0);
+        assert!(writer.peek_next_packet().is_none());
+        assert_eq!(second.len(), 7 + 2 + 7 * 3 + 4);
+        parser.parse(&second).unwrap();
+        assert_eq!(parser.pop_packet().unwrap().sequence_no(), 2);
+        assert!(parser.pop_packet().is_none());
+
+        let mut third = vec![];
+        writer.write(&mut third).unwrap();
+        parser.parse(&third).unwrap();
+        assert!(!parser.had_cc_data_section());
+    }
+
+    #[test]
+    fn writer_single_field_cea608_roundtrip() {
+        test_init_log();
+        for field2 in [false, true] {
+            let framerate = Framerate::from_id(0x4).unwrap();
+            let mut writer = CDPWriter::new(framerate);
+            let pair = |byte1| {
+                if field2 {
+                    Cea608::Field2(0x94, byte1)
+                } else {
+                    Cea608::Field1(0x94, byte1)
+                }
+            };
+            for i in 0..3 {
+                writer.push_cea608(pair(0x20 + i));
+            }
+            for seq_no in 0..3 {
+                let mut pack = DTVCCPacket::new(seq_no);
+                let mut service = Service::new(1);
+                service.push_code(&tables::Code::LatinCapitalA).unwrap();
+                pack.push_service(service).unwrap();
+                writer.push_packet(pack);
+            }
+
+            let mut parser = CDPParser::new();
+            let mut cea608: Vec<Cea608> = vec![];
+            let mut seq_nos = vec![];
+            for _ in 0..8 {
+                let len = writer.serialized_len();
+                let mut written = vec![];
+                writer.write(&mut written).unwrap();
+                assert_eq!(len, written.len());
+                parser.parse(&written).unwrap();
+                // field 1 padding is written before field 2 data
+                cea608.extend(
+                    parser
+                        .cea608()
+                        .unwrap_or_default()
+                        .iter()
+                        .filter(|pair| **pair != Cea608::Field1(0x80, 0x80)),
+                );
+                seq_nos.extend(parser.drain_packets().map(|packet| packet.sequence_no()));
+            }
+            assert_eq!(cea608, [pair(0x20), pair(0x21), pair(0x22)]);
+            assert_eq!(seq_nos, [0, 1, 2]);
+        }
+
+        // a single field 1 pair at 30000/1001 used to be written with a cc_count of 2
+        let mut writer = CDPWriter::new(Framerate::from_id(0x4).unwrap());
+        writer.push_cea608(Cea608::Field1(0x94, 0x20));
+        let mut written = vec![];
+        writer.write(&mut written).unwrap();
+        assert_eq!(written[7..12], [0x72, 0xe1, 0xfc, 0x94, 0x20]);
+    }
+
+    #[test]
+    fn writer_builder() {
+        test_init_log();
+        let framerate = Framerate::from_id(0x5).unwrap();
+        let mut writer = CDPWriter::builder(framerate)
+            .padding(true)
+            .auto_sequence(true)
+            .caption_service_active(true)
+            .sequence_count(0xffff)
+            .build();
+        assert!(writer.output_padding());
+        assert!(writer.auto_sequence());
+        assert!(writer.caption_service_active());
+
+        let mut parser = CDPParser::new();
+        for sequence in [0xffff, 0x0000, 0x0001] {
+            let mut written = vec![];
+            writer.write(&mut written).unwrap();
+            // fully padded at 30fps
+            assert_eq!(
+                written.len(),
+                HEADER_LEN
+                    + CC_DATA_HEADER_LEN
+                    + framerate.max_cc_count() as usize * 3
+                    + FOOTER_LEN
+            );
+            assert_eq!(
+                written[4],
+                Flags::CC_DATA_PRESENT | Flags::CAPTION_SERVICE_ACTIVE | Flags::RESERVED
+            );
+            parser.parse(&written).unwrap();
+            assert_eq!(parser.sequence(), sequence);
+        }
+
+        let default = CDPWriter::builder(framerate).build();
+        assert!(!default.output_padding());
+        assert!(!default.auto_sequence());
+        assert!(!default.caption_service_active());
+    }
+
+    #[test]
+    fn writer_section_order() {
+        test_init_log();
+        let framerate = Framerate::from_id(0x4).unwrap();
+        let mut writer = CDPWriter::new(framerate);
+        assert_eq!(
+            writer.section_order(),
+            [CdpSection::TimeCode, CdpSection::CcData]
+        );
+        for order in [
+            &[CdpSection::TimeCode][..],
+            &[CdpSection::TimeCode, CdpSection::TimeCode],
+            &[CdpSection::CcData, CdpSection::ServiceInfo],
+            &[CdpSection::TimeCode, CdpSection::CcData, CdpSection::Footer],
+        ] {
+            assert_eq!(
+                writer.set_section_order(order),
+                Err(WriterError::InvalidSectionOrder)
+            );
+        }
+        writer
+            .set_section_order(&[CdpSection::CcData, CdpSection::TimeCode])
+            .unwrap();
+        assert_eq!(
+            writer.section_order(),
+            [CdpSection::CcData, CdpSection::TimeCode]
+        );
+
+        writer.set_time_code(Some(TimeCode {
+            hours: 1,
+            minutes: 2,
+            seconds: 3,
+            frames: 4,
+            field: 0,
+            drop_frame: false,
+        }));
+        writer.push_cea608(Cea608::Field1(0x94, 0x20));
+        let mut written = vec![];
+        writer.write(&mut written).unwrap();
+        let expected = build_cdp(
+            0x4,
+            0xc1,
+            0x0,
+            &[0x72, 0xe1, 0xfc, 0x94, 0x20, 0x71, 0xc1, 0x82, 0x03, 0x04],
+        );
+        assert_eq!(written, expected);
+        verify_checksum(&written).unwrap();
+    }
+
+    #[test]
+    fn writer_max_len() {
+        test_init_log();
+        let framerate = Framerate::from_id(0x5).unwrap();
+        let mut writer = CDPWriter::builder(framerate).padding(true).build();
+        assert_eq!(writer.max_len(), u8::MAX);
+        let full_len = writer.serialized_len();
+        writer.set_max_len(full_len as u8 - 1);
+        let mut written = vec![];
+        assert_eq!(
+            writer.write(&mut written),
+            Err(WriterError::PacketTooLarge {
+                len: full_len,
+                max_len: full_len as u8 - 1
+            })
+        );
+        assert!(written.is_empty());
+
+        writer.set_max_len(full_len as u8);
+        writer.write(&mut written).unwrap();
+        assert_eq!(written.len(), full_len);
+    }
+
+    #[test]
+    fn writer_cea608_padding() {
+        test_init_log();
+        let framerate = Framerate::from_id(0x5).unwrap();
+        let cc_data = |written: &[u8]| written[9..written.len() - 4].to_vec();
+
+        let mut writer = CDPWriter::builder(framerate).padding(true).build();
+        assert!(writer.output_cea608_padding());
+        let mut written = vec![];
+        writer.write(&mut written).unwrap();
+        let mut expected = vec![0xf8, 0x80, 0x80, 0xf9, 0x80, 0x80];
+        expected.extend([0xfa, 0x00, 0x00].repeat(18));
+        assert_eq!(cc_data(&written), expected);
+
+        let mut writer = CDPWriter::builder(framerate)
+            .padding(true)
+            .cea608_padding(false)
+            .build();
+        assert!(writer.output_padding());
+        assert!(!writer.output_cea608_padding());
+        written.clear();
+        writer.write(&mut written).unwrap();
+        assert_eq!(cc_data(&written), [0xfa, 0x00, 0x00].repeat(20));
+        assert_eq!(writer.last_padding_count(), 20);
+
+        writer.push_cea608(Cea608::Field1(0x94, 0x20));
+        writer.set_output_cea608_padding(true);
+        written.clear();
+        writer.write(&mut written).unwrap();
+        assert_eq!(writer.last_padding_count(), 19);
+
+        writer.set_output_padding(false);
+        written.clear();
+        writer.write(&mut written).unwrap();
+        assert_eq!(writer.last_padding_count(), 0);
+    }
+
+    #[test]
+    fn writer_cea608_fields() {
+        test_init_log();
+        let framerate = Framerate::from_id(0x5).unwrap();
+        let cc_data = |written: &[u8]| written[9..written.len() - 4].to_vec();
+
+        let mut writer = CDPWriter::builder(framerate).padding(true).build();
+        assert_eq!(writer.cea608_fields(), Cea608Fields::Both);
+        writer.set_cea608_fields(Cea608Fields::Field1Only);
+        let mut written = vec![];
+        writer.write(&mut written).unwrap();
+        let mut expected = vec![0xf8, 0x80, 0x80];
+        expected.extend([0xfa, 0x00, 0x00].repeat(19));
+        assert_eq!(cc_data(&written), expected);
+
+        writer.set_cea608_fields(Cea608Fields::Field2Only);
+        written.clear();
+        writer.write(&mut written).unwrap();
+        let mut expected = vec![0xf9, 0x80, 0x80];
+        expected.extend([0xfa, 0x00, 0x00].repeat(19));
+        assert_eq!(cc_data(&written), expected);
+
+        // only CEA-608 padding
+        writer.set_output_padding(false);
+        writer.set_output_cea608_padding(true);
+        writer.set_cea608_fields(Cea608Fields::Field1Only);
+        written.clear();
+        writer.write(&mut written).unwrap();
+        assert_eq!(written[8], 0xe1);
+        assert_eq!(cc_data(&written), [0xf8, 0x80, 0x80]);
+
+        // pushed data for the other field is still written
+        writer.push_cea608(Cea608::Field1(0x94, 0x20));
+        writer.push_cea608(Cea608::Field2(0x15, 0x20));
+        written.clear();
+        writer.write(&mut written).unwrap();
+        assert_eq!(cc_data(&written), [0xfc, 0x94, 0x20, 0xfd, 0x15, 0x20]);
+    }
+
+    #[test]
+    fn writer_cc_data_writer_mut() {
+        test_init_log();
+        let framerate = Framerate::from_id(0x5).unwrap();
+        let mut writer = CDPWriter::new(framerate);
+        assert!(!writer.output_padding());
+        writer.cc_data_writer_mut().set_output_padding(true);
+        assert!(writer.output_padding());
+        let mut written = vec![];
+        writer.write(&mut written).unwrap();
+        assert_eq!(written[7], 0x72);
+        assert_eq!(written[8], 0xe0 | framerate.max_cc_count());
+    }
+
+    #[test]
+    fn serialized_len_matches_write() {
+        test_init_log();
+        let time_code = TimeCode {
+            hours: 1,
+            minutes: 2,
+            seconds: 3,
+            frames: 4,
+            field: 0,
+            drop_frame: false,
+        };
+        for framerate in FRAMERATES.iter() {
+            for with_time_code in [false, true] {
+                for n_codes in [0, 1, 20] {
+                    info!("framerate {framerate:?}, time code {with_time_code}, codes {n_codes}");
+                    let mut writer = CDPWriter::new(*framerate);
+                    if n_codes > 0 {
+                        let mut pack = DTVCCPacket::new(0);
+                        let mut service = Service::new(1);
+                        for _ in 0..n_codes {
+                            service.push_code(&tables::Code::LatinCapitalA).unwrap();
+                        }
+                        pack.push_service(service).unwrap();
+                        writer.push_packet(pack);
+                        writer.push_cea608(Cea608::Field1(0x94, 0x20));
+                    }
+                    if with_time_code {
+                        writer.set_time_code(Some(time_code));
+                    }
+                    let len = writer.serialized_len();
+                    // calling again must not take any more data
+                    assert_eq!(len, writer.serialized_len());
+                    let mut written = vec![];
+                    writer.write(&mut written).unwrap();
+                    assert_eq!(len, written.len());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn serialized_len_does_not_take_data() {
+        test_init_log();
+        let push_data = |writer: &mut CDPWriter| {
+            let mut pack = DTVCCPacket::new(0);
+            let mut service = Service::new(1);
+            service.push_code(&tables::Code::LatinCapitalA).unwrap();
+            pack.push_service(service).unwrap();
+            writer.push_packet(pack);
+            writer.push_cea608(Cea608::Field1(0x94, 0x20));
+        };
+        let framerate = Framerate::from_id(0x4).unwrap();
+        let mut previewed = CDPWriter::new(framerate);
+        let mut expected = CDPWriter::new(framerate);
+        assert_eq!(previewed.serialized_len(), 11);
+        assert_eq!(previewed.flags_byte(), 0x01);
+        push_data(&mut previewed);
+        push_data(&mut expected);
+        assert_eq!(previewed.serialized_len(), 11 + 2 + 3 * 3);
+        assert_eq!(previewed.flags_byte(), 0x41);
+
+        let mut written = vec![];
+        previewed.write(&mut written).unwrap();
+        let mut expected_written = vec![];
+        expected.write(&mut expected_written).unwrap();
+        assert_eq!(written, expected_written);
+    }
+
+    #[test]
+    fn serialized_len_matches_write_configurations() {
+        test_init_log();
+        for framerate in FRAMERATES.iter() {
+            for (padding, cea608_padding) in [(false, false), (false, true), (true, true)] {
+                for cea608_fields in [
+                    Cea608Fields::Both,
+                    Cea608Fields::Field1Only,
+                    Cea608Fields::Field2Only,
+                ] {
+                    info!(
+                        "framerate {framerate:?}, padding {padding}/{cea608_padding}, \
+                         fields {cea608_fields:?}"
+                    );
+                    let mut writer = CDPWriter::new(*framerate);
+                    writer.set_output_padding(padding);
+                    writer.set_output_cea608_padding(cea608_padding);
+                    writer.set_cea608_fields(cea608_fields);
+                    for seq_no in 0..4 {
+                        let mut pack = DTVCCPacket::new(seq_no);
+                        let mut service = Service::new(1);
+                        for _ in 0..(seq_no as usize * 8 + 3) {
+                            service.push_code(&tables::Code::LatinCapitalA).unwrap();
+                        }
+                        pack.push_service(service).unwrap();
+                        writer.push_packet(pack);
+                    }
+                    for i in 0..5 {
+                        writer.push_cea608(Cea608::Field1(0x94, 0x20 + i));
+                    }
+                    for i in 0..3 {
+                        writer.push_cea608(Cea608::Field2(0x15, 0x20 + i));
+                    }
+                    for _ in 0..16 {
+                        let len = writer.serialized_len();
+                        let flags = writer.flags_byte();
+                        let mut written = vec![];
+                        writer.write(&mut written).unwrap();
+                        assert_eq!(len, written.len());
+                        assert_eq!(flags, written[4]);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn cc_data_roundtrip() {
+        test_init_log();
+        let cdp = build_cdp(
+            0x4,
+            0x41,
+            0x0,
+            &[
+                0x72, 0xe3, 0xfc, 0x94, 0x20, 0xff, 0x02, 0x21, 0xfe, 0x41, 0x00,
+            ],
+        );
+        let mut parser = CDPParser::new();
+        parser.parse(&cdp).unwrap();
+        let cc_data = parser.take_cc_data().unwrap();
+        assert_eq!(
+            cc_data,
+            [0xc3, 0xff, 0xfc, 0x94, 0x20, 0xff, 0x02, 0x21, 0xfe, 0x41, 0x00]
+        );
+        assert_eq!(parser.take_cc_data(), None);
+
+        let mut writer = CDPWriter::new(Framerate::from_id(0x4).unwrap());
+        writer.push_cea608(Cea608::Field1(0x94, 0x2c));
+        writer.set_cc_data(&cc_data).unwrap();
+        let mut written = vec![];
+        writer.write(&mut written).unwrap();
+        assert_eq!(written, cdp);
+
+        // queued data is written afterwards
+        written.clear();
+        writer.write(&mut written).unwrap();
+        parser.parse(&written).unwrap();
+        assert_eq!(
+            parser.take_cc_data().unwrap(),
+            [0xc1, 0xff, 0xfc, 0x94, 0x2c]
+        );
+
+        assert_eq!(
+            writer.set_cc_data(&[0xc2, 0xff, 0xfc, 0x80, 0x80]),
+            Err(WriterError::InvalidCcData)
+        );
+        let mut too_many = vec![0xc0 | 21, 0xff];
+        too_many.extend([0xfa, 0x00, 0x00].repeat(21));
+        assert_eq!(
+            writer.set_cc_data(&too_many),
+            Err(WriterError::InvalidCcData)
+        );
+
+        // a CDP without cc_data has no cc_data to take
+        parser.parse(&build_cdp(0x4, 0x01, 0x0, &[])).unwrap();
+        assert_eq!(parser.take_cc_data(), None);
+    }
 }
 
 #[cfg(test)]
@@ -927,4 +5072,21 @@ pub(crate) mod tests {
     pub fn test_init_log() {
         Lazy::force(&TRACING);
     }
+
+    /// Build a CDP from the provided sections with a correct length and checksum
+    pub fn build_cdp(framerate_id: u8, flags: u8, sequence: u16, sections: &[u8]) -> Vec<u8> {
+        let mut data = vec![
+            0x96,
+            0x69,
+            (sections.len() + 11) as u8,
+            framerate_id << 4 | 0x0f,
+            flags,
+            (sequence >> 8) as u8,
+            (sequence & 0xff) as u8,
+        ];
+        data.extend_from_slice(sections);
+        data.extend_from_slice(&[0x74, (sequence >> 8) as u8, (sequence & 0xff) as u8]);
+        data.push(crate::cdp_checksum(&data));
+        data
+    }
 }
@@ -0,0 +1,724 @@
+// Copyright (C) 2023 Matthew Waters <matthew@centricular.com>
+//
+// Licensed under the MIT license <LICENSE-MIT> or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! MacCaption (`.mcc`) file support
+//!
+//! An MCC file is a text file containing a header followed by a line for each frame.  Each line
+//! contains a time code and a hex encoded (with some run-length compression) ANC packet
+//! containing a CDP.
+
+use crate::anc::ANC_CDP_ID;
+use crate::{Framerate, TimeCode};
+
+/// Errors that can occur when reading or writing MCC files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MccError {
+    /// Reading the data failed
+    Io(std::io::ErrorKind),
+    /// The header is missing, incomplete or invalid
+    InvalidHeader,
+    /// The time code rate in the header is not supported
+    UnknownTimeCodeRate,
+    /// A caption line could not be parsed
+    InvalidLine {
+        /// The line number (starting at 1) of the invalid line
+        line: usize,
+    },
+    /// The ANC packet in a caption line does not contain a CDP
+    NotCdp {
+        /// The line number (starting at 1) of the line
+        line: usize,
+    },
+    /// The time code of a written CDP is earlier than the previously written time code
+    NonIncreasingTimeCode,
+    /// The CDP is larger than can be stored in an ANC packet
+    CdpTooLarge,
+}
+
+impl From<std::io::Error> for MccError {
+    fn from(value: std::io::Error) -> Self {
+        MccError::Io(value.kind())
+    }
+}
+
+impl std::fmt::Display for MccError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(&format!("{self:?}"))
+    }
+}
+
+/// The time code rate of an MCC file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimeCodeRate {
+    fps: u8,
+    drop_frame: bool,
+}
+
+impl TimeCodeRate {
+    /// Create a new [`TimeCodeRate`].  Only 24, 25, 30, 50 and 60 fps are supported and drop
+    /// frame is only supported for 30 and 60 fps.
+    pub fn new(fps: u8, drop_frame: bool) -> Option<Self> {
+        match (fps, drop_frame) {
+            (24 | 25 | 50, false) | (30 | 60, _) => Some(Self { fps, drop_frame }),
+            _ => None,
+        }
+    }
+
+    /// The [`TimeCodeRate`] for `framerate`.  Returns `None` if drop frame time codes are
+    /// requested for a framerate that does not support them.
+    pub fn from_framerate(framerate: Framerate, drop_frame: bool) -> Option<Self> {
+        let fps = (framerate.numer() as u64).div_ceil(framerate.denom() as u64);
+        Self::new(fps as u8, drop_frame)
+    }
+
+    /// The nominal number of frames per second
+    pub fn fps(&self) -> u8 {
+        self.fps
+    }
+
+    /// Whether drop frame time codes are used
+    pub fn drop_frame(&self) -> bool {
+        self.drop_frame
+    }
+
+    /// The [`Framerate`] for this time code rate.
+    ///
+    /// MCC files do not distinguish between integer and non drop frame NTSC framerates (e.g. 30
+    /// and 30000/1001) so integer framerates are returned for non drop frame time code rates.
+    /// The framerate contained in each CDP should be preferred where possible.
+    pub fn framerate(&self) -> Framerate {
+        let id = match (self.fps, self.drop_frame) {
+            (24, _) => 0x2,
+            (25, _) => 0x3,
+            (30, true) => 0x4,
+            (30, false) => 0x5,
+            (50, _) => 0x6,
+            (60, true) => 0x7,
+            (60, false) => 0x8,
+            _ => unreachable!(),
+        };
+        Framerate::from_id(id).unwrap()
+    }
+}
+
+impl std::str::FromStr for TimeCodeRate {
+    type Err = MccError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (fps, drop_frame) = match s.strip_suffix("DF") {
+            Some(fps) => (fps, true),
+            None => (s, false),
+        };
+        let fps = fps.parse().map_err(|_| MccError::UnknownTimeCodeRate)?;
+        Self::new(fps, drop_frame).ok_or(MccError::UnknownTimeCodeRate)
+    }
+}
+
+impl std::fmt::Display for TimeCodeRate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.drop_frame {
+            write!(f, "{}DF", self.fps)
+        } else {
+            write!(f, "{}", self.fps)
+        }
+    }
+}
+
+/// Reads the CDPs contained in an MCC file
+#[derive(Debug)]
+pub struct MccReader<R> {
+    reader: R,
+    version: String,
+    uuid: Option<String>,
+    creation_program: Option<String>,
+    creation_date: Option<String>,
+    creation_time: Option<String>,
+    time_code_rate: TimeCodeRate,
+    line_no: usize,
+    pending_line: Option<String>,
+}
+
+impl<R: std::io::BufRead> MccReader<R> {
+    /// Create a new [`MccReader`] reading the MCC header from `reader`
+    pub fn new(mut reader: R) -> Result<Self, MccError> {
+        let mut line_no = 0;
+        let mut line = String::new();
+        let mut next_line = |reader: &mut R, line: &mut String| -> Result<bool, MccError> {
+            line.clear();
+            line_no += 1;
+            Ok(reader.read_line(line)? > 0)
+        };
+
+        if !next_line(&mut reader, &mut line)? {
+            return Err(MccError::InvalidHeader);
+        }
+        let version = line
+            .trim_start_matches('\u{feff}')
+            .trim_end()
+            .strip_prefix("File Format=MacCaption_MCC ")
+            .ok_or(MccError::InvalidHeader)?
+            .to_string();
+        trace!("MCC version {version}");
+
+        let mut uuid = None;
+        let mut creation_program = None;
+        let mut creation_date = None;
+        let mut creation_time = None;
+        let mut time_code_rate = None;
+        let mut pending_line = None;
+        while next_line(&mut reader, &mut line)? {
+            let trimmed = line.trim_end();
+            if trimmed.is_empty() || trimmed.starts_with("//") {
+                continue;
+            }
+            if trimmed.starts_with(|c: char| c.is_ascii_digit()) {
+                pending_line = Some(trimmed.to_string());
+                break;
+            }
+            let (key, value) = trimmed.split_once('=').ok_or(MccError::InvalidHeader)?;
+            trace!("header {key}={value}");
+            match key {
+                "UUID" => uuid = Some(value.to_string()),
+                "Creation Program" => creation_program = Some(value.to_string()),
+                "Creation Date" => creation_date = Some(value.to_string()),
+                "Creation Time" => creation_time = Some(value.to_string()),
+                "Time Code Rate" => time_code_rate = Some(value.parse()?),
+                _ => debug!("ignoring unknown header {key}={value}"),
+            }
+        }
+
+        Ok(Self {
+            reader,
+            version,
+            uuid,
+            creation_program,
+            creation_date,
+            creation_time,
+            time_code_rate: time_code_rate.ok_or(MccError::InvalidHeader)?,
+            line_no,
+            pending_line,
+        })
+    }
+
+    /// The version of the MCC file format, e.g. `V1.0`
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// The UUID in the header
+    pub fn uuid(&self) -> Option<&str> {
+        self.uuid.as_deref()
+    }
+
+    /// The program that created the file
+    pub fn creation_program(&self) -> Option<&str> {
+        self.creation_program.as_deref()
+    }
+
+    /// The date the file was created
+    pub fn creation_date(&self) -> Option<&str> {
+        self.creation_date.as_deref()
+    }
+
+    /// The time the file was created
+    pub fn creation_time(&self) -> Option<&str> {
+        self.creation_time.as_deref()
+    }
+
+    /// The time code rate in the header
+    pub fn time_code_rate(&self) -> TimeCodeRate {
+        self.time_code_rate
+    }
+
+    /// Read the next CDP and its [`TimeCode`].  Returns `None` at the end of the file.
+    pub fn next_cdp(&mut self) -> Result<Option<(TimeCode, Vec<u8>)>, MccError> {
+        let line = match self.pending_line.take() {
+            Some(line) => line,
+            None => loop {
+                let mut line = String::new();
+                if self.reader.read_line(&mut line)? == 0 {
+                    return Ok(None);
+                }
+                self.line_no += 1;
+                let trimmed = line.trim_end();
+                if !trimmed.is_empty() && !trimmed.starts_with("//") {
+                    break trimmed.to_string();
+                }
+            },
+        };
+        let line_no = self.line_no;
+        let invalid = MccError::InvalidLine { line: line_no };
+
+        let (time_code, data) = line.split_once('\t').ok_or(invalid)?;
+        let time_code = parse_time_code(time_code, self.time_code_rate).ok_or(invalid)?;
+        let data = decode_payload(data.trim()).ok_or(invalid)?;
+        trace!("line {line_no} time code {time_code:?} data {data:x?}");
+
+        if data.len() < 3 || data[..2] != ANC_CDP_ID {
+            return Err(MccError::NotCdp { line: line_no });
+        }
+        let len = data[2] as usize;
+        if data.len() < 3 + len {
+            return Err(invalid);
+        }
+        // any trailing data (the ANC checksum) is ignored
+        Ok(Some((time_code, data[3..3 + len].to_vec())))
+    }
+}
+
+impl<R: std::io::BufRead> Iterator for MccReader<R> {
+    type Item = Result<(TimeCode, Vec<u8>), MccError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_cdp().transpose()
+    }
+}
+
+/// Parse a time code of the form `HH:MM:SS:FF` or `HH:MM:SS;FF` (drop frame), optionally
+/// followed by `.F` for the field.
+///
+/// Above 30fps, MCC time codes count every frame while CDP time codes count pairs of frames
+/// with the field flag identifying the second frame of the pair.
+fn parse_time_code(s: &str, rate: TimeCodeRate) -> Option<TimeCode> {
+    let (s, field) = match s.split_once('.') {
+        Some((s, field)) => (s, field.parse::<u8>().ok()?),
+        None => (s, 0),
+    };
+    if field > 1 || s.len() != 11 || !s.is_ascii() {
+        return None;
+    }
+    let drop_frame = match &s[8..9] {
+        ";" => true,
+        ":" => rate.drop_frame(),
+        _ => return None,
+    };
+    let mut values = s[..8].split(':').chain(std::iter::once(&s[9..]));
+    let mut next = || -> Option<u8> {
+        let v = values.next()?;
+        if v.len() != 2 {
+            return None;
+        }
+        v.parse().ok()
+    };
+    let hours = next()?;
+    let minutes = next()?;
+    let seconds = next()?;
+    let frames = next()?;
+    if hours > 23 || minutes > 59 || seconds > 59 || frames >= rate.fps() {
+        return None;
+    }
+    let (frames, field) = if rate.fps() > 30 {
+        (frames / 2, frames % 2)
+    } else {
+        (frames, field)
+    };
+    Some(TimeCode {
+        hours,
+        minutes,
+        seconds,
+        frames,
+        field,
+        drop_frame,
+    })
+}
+
+/// Format a time code as the inverse of [`parse_time_code`]
+fn format_time_code(time_code: &TimeCode, rate: TimeCodeRate) -> String {
+    if rate.fps() > 30 {
+        let time_code = TimeCode {
+            frames: time_code.frames * 2 + (time_code.field & 0x1),
+            field: 0,
+            ..*time_code
+        };
+        time_code.to_string()
+    } else if time_code.field & 0x1 > 0 {
+        format!("{time_code}.1")
+    } else {
+        time_code.to_string()
+    }
+}
+
+/// The bytes represented by a compression code
+fn expand_code(c: u8) -> Option<&'static [u8]> {
+    static PADDING: [u8; 27] = [
+        0xFA, 0x00, 0x00, 0xFA, 0x00, 0x00, 0xFA, 0x00, 0x00, 0xFA, 0x00, 0x00, 0xFA, 0x00, 0x00,
+        0xFA, 0x00, 0x00, 0xFA, 0x00, 0x00, 0xFA, 0x00, 0x00, 0xFA, 0x00, 0x00,
+    ];
+    Some(match c {
+        b'G'..=b'O' => &PADDING[..(c - b'G' + 1) as usize * 3],
+        b'P' => &[0xFB, 0x80, 0x80],
+        b'Q' => &[0xFC, 0x80, 0x80],
+        b'R' => &[0xFD, 0x80, 0x80],
+        b'S' => &[0x96, 0x69],
+        b'T' => &[0x61, 0x01],
+        b'U' => &[0xE1, 0x00, 0x00, 0x00],
+        b'Z' => &[0x00],
+        _ => return None,
+    })
+}
+
+/// Decode the hex encoded and compressed payload of a caption line
+fn decode_payload(s: &str) -> Option<Vec<u8>> {
+    let mut ret = Vec::with_capacity(s.len() / 2);
+    let mut chars = s.bytes().filter(|c| !c.is_ascii_whitespace());
+    while let Some(c) = chars.next() {
+        if let Some(bytes) = expand_code(c) {
+            ret.extend_from_slice(bytes);
+            continue;
+        }
+        let high = (c as char).to_digit(16)?;
+        let low = (chars.next()? as char).to_digit(16)?;
+        ret.push((high << 4 | low) as u8);
+    }
+    Some(ret)
+}
+
+/// Hex encode `data` using the compression codes where possible
+fn encode_payload(data: &[u8]) -> String {
+    let mut ret = String::with_capacity(data.len() * 2);
+    let mut idx = 0;
+    'outer: while idx < data.len() {
+        let rem = &data[idx..];
+        let mut padding = 0;
+        while padding < 9 && rem[padding * 3..].starts_with(&[0xFA, 0x00, 0x00]) {
+            padding += 1;
+        }
+        if padding > 0 {
+            ret.push((b'G' + padding as u8 - 1) as char);
+            idx += padding * 3;
+            continue;
+        }
+        for c in [b'P', b'Q', b'R', b'S', b'T', b'U', b'Z'] {
+            let bytes = expand_code(c).unwrap();
+            if rem.starts_with(bytes) {
+                ret.push(c as char);
+                idx += bytes.len();
+                continue 'outer;
+            }
+        }
+        ret.push_str(&format!("{:02X}", rem[0]));
+        idx += 1;
+    }
+    ret
+}
+
+static MCC_HEADER_COMMENT: &str = "\
+///////////////////////////////////////////////////////////////////////////////////\r
+// Computer Prompting and Captioning Company\r
+// Ancillary Data Packet Transfer File\r
+//\r
+// Permission to generate this format is granted provided that\r
+//   1. This ANC Transfer file format is used on an as-is basis and no warranty is given, and\r
+//   2. This entire descriptive information text is included in a generated .mcc file.\r
+//\r
+// General file format:\r
+//   HH:MM:SS:FF(tab)[Hexadecimal ANC data in groups of 2 characters]\r
+//     Hexadecimal data starts with the Ancillary Data Packet DID (Data ID defined in S291M)\r
+//       and concludes with the Check Sum following the User Data Words.\r
+//     Each time code line must contain at most one complete ancillary data packet.\r
+//     To transfer additional ANC Data successive lines may contain identical time code.\r
+//     Time Code Rate=[24, 25, 30, 30DF, 50, 60]\r
+//\r
+//   ANC data bytes may be represented by one ASCII character according to the following schema:\r
+//     G  FAh 00h 00h\r
+//     H  2 x (FAh 00h 00h)\r
+//     I  3 x (FAh 00h 00h)\r
+//     J  4 x (FAh 00h 00h)\r
+//     K  5 x (FAh 00h 00h)\r
+//     L  6 x (FAh 00h 00h)\r
+//     M  7 x (FAh 00h 00h)\r
+//     N  8 x (FAh 00h 00h)\r
+//     O  9 x (FAh 00h 00h)\r
+//     P  FBh 80h 80h\r
+//     Q  FCh 80h 80h\r
+//     R  FDh 80h 80h\r
+//     S  96h 69h\r
+//     T  61h 01h\r
+//     U  E1h 00h 00h 00h\r
+//     Z  00h\r
+//\r
+///////////////////////////////////////////////////////////////////////////////////\r
+";
+
+/// Writes CDPs into an MCC file
+#[derive(Debug)]
+pub struct MccWriter<W> {
+    writer: W,
+    time_code_rate: TimeCodeRate,
+    uuid: Option<String>,
+    creation_program: String,
+    creation_date: Option<String>,
+    creation_time: Option<String>,
+    header_written: bool,
+    last_time_code: Option<TimeCode>,
+}
+
+impl<W: std::io::Write> MccWriter<W> {
+    /// Create a new [`MccWriter`].  The header is written with the first CDP.
+    pub fn new(writer: W, time_code_rate: TimeCodeRate) -> Self {
+        Self {
+            writer,
+            time_code_rate,
+            uuid: None,
+            creation_program: String::from("cdp-types"),
+            creation_date: None,
+            creation_time: None,
+            header_written: false,
+            last_time_code: None,
+        }
+    }
+
+    /// Set the UUID written in the header.  MCC files are expected to contain a unique UUID.
+    pub fn set_uuid(&mut self, uuid: &str) {
+        self.uuid = Some(uuid.to_string());
+    }
+
+    /// Set the creating program written in the header.  Defaults to `cdp-types`.
+    pub fn set_creation_program(&mut self, creation_program: &str) {
+        self.creation_program = creation_program.to_string();
+    }
+
+    /// Set the creation date written in the header, e.g. `Thursday, June 01, 2023`
+    pub fn set_creation_date(&mut self, creation_date: &str) {
+        self.creation_date = Some(creation_date.to_string());
+    }
+
+    /// Set the creation time written in the header, e.g. `12:00:00`
+    pub fn set_creation_time(&mut self, creation_time: &str) {
+        self.creation_time = Some(creation_time.to_string());
+    }
+
+    fn write_header(&mut self) -> Result<(), MccError> {
+        if self.header_written {
+            return Ok(());
+        }
+        let w = &mut self.writer;
+        write!(w, "File Format=MacCaption_MCC V1.0\r\n\r\n")?;
+        w.write_all(MCC_HEADER_COMMENT.as_bytes())?;
+        write!(w, "\r\n")?;
+        if let Some(ref uuid) = self.uuid {
+            write!(w, "UUID={uuid}\r\n")?;
+        }
+        write!(w, "Creation Program={}\r\n", self.creation_program)?;
+        if let Some(ref creation_date) = self.creation_date {
+            write!(w, "Creation Date={creation_date}\r\n")?;
+        }
+        if let Some(ref creation_time) = self.creation_time {
+            write!(w, "Creation Time={creation_time}\r\n")?;
+        }
+        write!(w, "Time Code Rate={}\r\n\r\n", self.time_code_rate)?;
+        self.header_written = true;
+        Ok(())
+    }
+
+    /// Write a CDP at `time_code`.  Time codes must not decrease between calls.
+    ///
+    /// The CDP is wrapped in an ANC packet with a checksum of the 8 least significant bits of the
+    /// sum of the ANC packet bytes.
+    pub fn write_cdp(&mut self, time_code: TimeCode, cdp: &[u8]) -> Result<(), MccError> {
+        if cdp.len() > u8::MAX as usize {
+            return Err(MccError::CdpTooLarge);
+        }
+        if self.last_time_code.is_some_and(|last| time_code < last) {
+            return Err(MccError::NonIncreasingTimeCode);
+        }
+        self.write_header()?;
+
+        let data = crate::anc::wrap_cdp_8bit(cdp);
+
+        write!(
+            self.writer,
+            "{}\t{}\r\n",
+            format_time_code(&time_code, self.time_code_rate),
+            encode_payload(&data)
+        )?;
+        self.last_time_code = Some(time_code);
+        Ok(())
+    }
+
+    /// Write the header if no CDPs have been written and return the inner writer
+    pub fn finish(mut self) -> Result<W, MccError> {
+        self.write_header()?;
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tests::*;
+    use crate::CDPParser;
+
+    static MCC_FILE: &str = "File Format=MacCaption_MCC V1.0\r
+\r
+///////////////////////////////////////////////////////////////////////////////////\r
+// Computer Prompting and Captioning Company\r
+///////////////////////////////////////////////////////////////////////////////////\r
+\r
+UUID=CA6A5AE4-E473-4B7A-8FAD-22B0CCFB59E7\r
+Creation Program=Test\r
+Creation Date=Thursday, June 01, 2023\r
+Creation Time=12:00:00\r
+Time Code Rate=25\r
+\r
+17:59:57:18\tT18S183FC1123471D7D9D79872E2FF0221FE41Z741234A4BB\r
+17:59:57:19\tT13S133F41123472E2FF0221FE41Z741234B9C0\r
+";
+
+    #[test]
+    fn read_mcc() {
+        test_init_log();
+        let mut reader = MccReader::new(MCC_FILE.as_bytes()).unwrap();
+        assert_eq!(reader.version(), "V1.0");
+        assert_eq!(reader.uuid(), Some("CA6A5AE4-E473-4B7A-8FAD-22B0CCFB59E7"));
+        assert_eq!(reader.creation_program(), Some("Test"));
+        assert_eq!(reader.creation_date(), Some("Thursday, June 01, 2023"));
+        assert_eq!(reader.creation_time(), Some("12:00:00"));
+        assert_eq!(
+            reader.time_code_rate(),
+            TimeCodeRate::new(25, false).unwrap()
+        );
+        assert_eq!(
+            reader.time_code_rate().framerate(),
+            Framerate::from_id(0x3).unwrap()
+        );
+
+        let mut parser = CDPParser::new();
+        let (time_code, cdp) = reader.next_cdp().unwrap().unwrap();
+        assert_eq!(
+            time_code,
+            TimeCode {
+                hours: 17,
+                minutes: 59,
+                seconds: 57,
+                frames: 18,
+                field: 0,
+                drop_frame: false,
+            }
+        );
+        parser.parse(&cdp).unwrap();
+        assert_eq!(parser.sequence(), 0x1234);
+        assert!(parser.time_code().is_some());
+        assert!(parser.pop_packet().is_some());
+
+        let (time_code, cdp) = reader.next_cdp().unwrap().unwrap();
+        assert_eq!(time_code.frames, 19);
+        parser.parse(&cdp).unwrap();
+        assert!(parser.time_code().is_none());
+        assert!(parser.pop_packet().is_some());
+
+        assert_eq!(reader.next_cdp(), Ok(None));
+    }
+
+    #[test]
+    fn read_mcc_invalid() {
+        test_init_log();
+        assert_eq!(
+            MccReader::new("".as_bytes()).unwrap_err(),
+            MccError::InvalidHeader
+        );
+        assert_eq!(
+            MccReader::new("File Format=MacCaption_MCC V1.0\nTime Code Rate=29\n".as_bytes())
+                .unwrap_err(),
+            MccError::UnknownTimeCodeRate
+        );
+        let mut reader = MccReader::new(
+            "File Format=MacCaption_MCC V1.0\nTime Code Rate=30DF\n\n00:00:00;00\tXX\n00:00:00;01\t6200\n"
+                .as_bytes(),
+        )
+        .unwrap();
+        assert!(reader.time_code_rate().drop_frame());
+        assert_eq!(reader.next(), Some(Err(MccError::InvalidLine { line: 4 })));
+        assert_eq!(reader.next(), Some(Err(MccError::NotCdp { line: 5 })));
+        assert_eq!(reader.next(), None);
+    }
+
+    #[test]
+    fn write_read_mcc() {
+        test_init_log();
+        let cdps = [
+            (
+                TimeCode {
+                    hours: 1,
+                    minutes: 2,
+                    seconds: 3,
+                    frames: 4,
+                    field: 0,
+                    drop_frame: true,
+                },
+                crate::tests::build_cdp(
+                    0x4,
+                    0x41,
+                    0x0,
+                    &[0x72, 0xe2, 0xFA, 0x00, 0x00, 0xFA, 0x00, 0x00],
+                ),
+            ),
+            (
+                TimeCode {
+                    hours: 1,
+                    minutes: 2,
+                    seconds: 3,
+                    frames: 29,
+                    field: 1,
+                    drop_frame: true,
+                },
+                crate::tests::build_cdp(0x7, 0x41, 0x1, &[0x72, 0xe1, 0xFC, 0x94, 0x20]),
+            ),
+        ];
+        for fps in [30, 60] {
+            let rate = TimeCodeRate::new(fps, true).unwrap();
+            let mut writer = MccWriter::new(vec![], rate);
+            writer.set_uuid("CA6A5AE4-E473-4B7A-8FAD-22B0CCFB59E7");
+            writer.set_creation_date("Thursday, June 01, 2023");
+            writer.set_creation_time("12:00:00");
+            for (time_code, cdp) in cdps.iter() {
+                writer.write_cdp(*time_code, cdp).unwrap();
+            }
+            assert_eq!(
+                writer.write_cdp(cdps[0].0, &cdps[0].1),
+                Err(MccError::NonIncreasingTimeCode)
+            );
+            let written = writer.finish().unwrap();
+            debug!("{}", std::str::from_utf8(&written).unwrap());
+
+            let mut reader = MccReader::new(written.as_slice()).unwrap();
+            assert_eq!(reader.time_code_rate(), rate);
+            assert_eq!(reader.uuid(), Some("CA6A5AE4-E473-4B7A-8FAD-22B0CCFB59E7"));
+            assert_eq!(reader.creation_program(), Some("cdp-types"));
+            assert_eq!(reader.creation_date(), Some("Thursday, June 01, 2023"));
+            assert_eq!(reader.creation_time(), Some("12:00:00"));
+            for (time_code, cdp) in cdps.iter() {
+                assert_eq!(reader.next_cdp().unwrap(), Some((*time_code, cdp.clone())));
+            }
+            assert_eq!(reader.next_cdp(), Ok(None));
+        }
+    }
+
+    #[test]
+    fn encode_compression_codes() {
+        let data = [
+            0xFA, 0x00, 0x00, 0xFA, 0x00, 0x00, 0xFB, 0x80, 0x80, 0x96, 0x69, 0x61, 0x01, 0xE1,
+            0x00, 0x00, 0x00, 0x00, 0x0a, 0x00,
+        ];
+        let encoded = encode_payload(&data);
+        assert_eq!(encoded, "HPSTUZ0AZ");
+        assert_eq!(decode_payload(&encoded).unwrap(), data);
+    }
+
+    #[test]
+    fn decode_compression_codes() {
+        assert_eq!(
+            decode_payload("GHPQRSTUZ0a").unwrap(),
+            [
+                0xFA, 0x00, 0x00, 0xFA, 0x00, 0x00, 0xFA, 0x00, 0x00, 0xFB, 0x80, 0x80, 0xFC, 0x80,
+                0x80, 0xFD, 0x80, 0x80, 0x96, 0x69, 0x61, 0x01, 0xE1, 0x00, 0x00, 0x00, 0x00, 0x0a,
+            ]
+        );
+        assert!(decode_payload("0").is_none());
+        assert!(decode_payload("0X").is_none());
+    }
+}
@@ -0,0 +1,123 @@
+// Copyright (C) 2023 Matthew Waters <matthew@centricular.com>
+//
+// Licensed under the MIT license <LICENSE-MIT> or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Scenarist (`.scc`) file export
+//!
+//! An SCC file contains only the CEA-608 field 1 byte pairs of a caption stream.  Each line
+//! contains a time code followed by the hex encoded byte pairs with odd parity.
+
+use cea708_types::Cea608;
+
+use crate::{set_odd_parity, TimeCode};
+
+/// The first line of every SCC file
+const SCC_HEADER: &str = "Scenarist_SCC V1.0";
+
+/// Write the CEA-608 byte pairs in `captions` as an SCC file into `w`.
+///
+/// `captions` contains the [`TimeCode`] of each frame together with the [`Cea608`] byte pairs
+/// for that frame as returned by [`CDPParser::cea608`](crate::CDPParser::cea608).  SCC only
+/// supports field 1 data so any [`Cea608::Field2`] byte pairs are ignored as are frames that
+/// only contain padding.  The parity bit of every byte is recalculated.
+pub fn write_scc<'a, W, I>(w: &mut W, captions: I) -> Result<(), std::io::Error>
+where
+    W: std::io::Write,
+    I: IntoIterator<Item = (TimeCode, &'a [Cea608])>,
+{
+    write!(w, "{SCC_HEADER}\r\n\r\n")?;
+
+    for (time_code, cea608) in captions {
+        let pairs = cea608
+            .iter()
+            .filter_map(|pair| match pair {
+                Cea608::Field1(byte0, byte1) => {
+                    Some([set_odd_parity(*byte0), set_odd_parity(*byte1)])
+                }
+                Cea608::Field2(..) => None,
+            })
+            .collect::<Vec<_>>();
+        if pairs.iter().all(|pair| *pair == [0x80, 0x80]) {
+            trace!("skipping {time_code} without field 1 data");
+            continue;
+        }
+
+        write!(w, "{time_code}\t")?;
+        for (i, pair) in pairs.iter().enumerate() {
+            if i > 0 {
+                w.write_all(b" ")?;
+            }
+            write!(w, "{:02x}{:02x}", pair[0], pair[1])?;
+        }
+        w.write_all(b"\r\n\r\n")?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tests::*;
+    use crate::{CDPParser, Framerate};
+
+    #[test]
+    fn write_scc_from_parser() {
+        test_init_log();
+        let framerate = Framerate::from_id(0x4).unwrap();
+        let cdps = [
+            // 00:00:01;00 with a resume caption loading command on field 1 and data on field 2
+            build_cdp(
+                0x4,
+                0xc3,
+                0x1234,
+                &[
+                    0x71, 0xc0, 0x80, 0x01, 0x80, 0x72, 0xe2, 0xfc, 0x14, 0x20, 0xfd, 0x41, 0x42,
+                ],
+            ),
+            // 00:00:01;01 with only padding
+            build_cdp(
+                0x4,
+                0xc3,
+                0x1235,
+                &[0x71, 0xc0, 0x80, 0x01, 0x81, 0x72, 0xe1, 0xfc, 0x80, 0x80],
+            ),
+            // 00:00:01;02 with two field 1 pairs without parity
+            build_cdp(
+                0x4,
+                0xc3,
+                0x1236,
+                &[
+                    0x71, 0xc0, 0x80, 0x01, 0x82, 0x72, 0xe2, 0xfc, 0x48, 0x49, 0xfc, 0x21, 0x00,
+                ],
+            ),
+        ];
+
+        let mut parser = CDPParser::new();
+        let mut captions = vec![];
+        for cdp in cdps.iter() {
+            parser.parse(cdp).unwrap();
+            assert_eq!(parser.framerate(), Some(framerate));
+            let time_code = parser.time_code().unwrap();
+            let cea608 = parser.cea608().unwrap_or_default().to_vec();
+            captions.push((time_code, cea608));
+        }
+
+        let mut scc = vec![];
+        write_scc(
+            &mut scc,
+            captions
+                .iter()
+                .map(|(time_code, cea608)| (*time_code, cea608.as_slice())),
+        )
+        .unwrap();
+        assert_eq!(
+            std::str::from_utf8(&scc).unwrap(),
+            "Scenarist_SCC V1.0\r\n\r\n\
+             00:00:01;00\t9420\r\n\r\n\
+             00:00:01;02\tc849 a180\r\n\r\n"
+        );
+    }
+}